@@ -0,0 +1,76 @@
+#![feature(test)]
+
+extern crate test;
+extern crate yarn;
+
+use std::rc::Rc;
+
+use test::Bencher;
+
+use yarn::geometry::{Geometry, GeometryData};
+use yarn::object::Object;
+use yarn::yarn::{Tie, Yarn};
+
+const OBJECTS: usize = 100_000;
+const GEOMETRIES: usize = 1_000;
+
+fn scene() -> Vec<Object> {
+    let geometries: Vec<_> = (0..GEOMETRIES).map(|i| {
+        Rc::new(
+            GeometryData::Geometry(
+                Geometry::new(
+                    vec![(i as f32, 2.0, 3.0); 10],
+                    vec![(0.0, 1.0); 6],
+                    vec![(1, 2); 18]
+                ).unwrap()
+            )
+        )
+    }).collect();
+
+    (0..OBJECTS).map(|i| Object::new(geometries[i % GEOMETRIES].clone())).collect()
+}
+
+fn tie(objects: Vec<Object>) -> Yarn {
+    let mut yarn = Yarn::new();
+
+    for object in objects {
+        object.tie(&mut yarn);
+    }
+
+    yarn
+}
+
+#[bench]
+fn tie_100k_objects(b: &mut Bencher) {
+    b.iter(|| tie(scene()));
+}
+
+#[bench]
+fn untie_100k_objects(b: &mut Bencher) {
+    let bytes = tie(scene()).into_bytes().unwrap();
+
+    b.iter(|| {
+        let mut yarn = Yarn::from_bytes(&bytes).unwrap();
+        let mut count = 0;
+
+        while let Some(object) = Object::untie(&mut yarn) {
+            test::black_box(object);
+            count += 1;
+        }
+
+        assert_eq!(count, OBJECTS);
+    });
+}
+
+#[bench]
+fn untie_load_100k_objects(b: &mut Bencher) {
+    let bytes = tie(scene()).into_bytes().unwrap();
+
+    b.iter(|| {
+        let mut yarn = Yarn::from_bytes(&bytes).unwrap();
+
+        while let Some(object) = Object::untie(&mut yarn) {
+            test::black_box(object.geometry());
+        }
+    });
+}
@@ -0,0 +1,316 @@
+use std::cmp::Ordering;
+
+use super::geometry::first_use;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AttributeValues {
+    F32(Vec<f32>),
+    U16(Vec<u16>),
+    U8(Vec<u8>)
+}
+
+impl AttributeValues {
+    fn len(&self) -> usize {
+        match *self {
+            AttributeValues::F32(ref values) => values.len(),
+            AttributeValues::U16(ref values) => values.len(),
+            AttributeValues::U8(ref values) => values.len()
+        }
+    }
+
+    fn gather<I: Iterator<Item = usize>>(&self, components: usize, elements: I) -> AttributeValues {
+        match *self {
+            AttributeValues::F32(ref values) => {
+                AttributeValues::F32(gather(values, components, elements))
+            }
+            AttributeValues::U16(ref values) => {
+                AttributeValues::U16(gather(values, components, elements))
+            }
+            AttributeValues::U8(ref values) => {
+                AttributeValues::U8(gather(values, components, elements))
+            }
+        }
+    }
+
+    fn concat(&self, other: &AttributeValues) -> Option<AttributeValues> {
+        match *self {
+            AttributeValues::F32(ref a) => {
+                match *other {
+                    AttributeValues::F32(ref b) => Some(AttributeValues::F32(a.iter().chain(b).cloned().collect())),
+                    _ => None
+                }
+            }
+            AttributeValues::U16(ref a) => {
+                match *other {
+                    AttributeValues::U16(ref b) => Some(AttributeValues::U16(a.iter().chain(b).cloned().collect())),
+                    _ => None
+                }
+            }
+            AttributeValues::U8(ref a) => {
+                match *other {
+                    AttributeValues::U8(ref b) => Some(AttributeValues::U8(a.iter().chain(b).cloned().collect())),
+                    _ => None
+                }
+            }
+        }
+    }
+
+    fn condense(&self, components: usize) -> (AttributeValues, Vec<usize>) {
+        match *self {
+            AttributeValues::F32(ref values) => {
+                let (values, indices) = condense(values, components);
+                (AttributeValues::F32(values), indices)
+            }
+            AttributeValues::U16(ref values) => {
+                let (values, indices) = condense(values, components);
+                (AttributeValues::U16(values), indices)
+            }
+            AttributeValues::U8(ref values) => {
+                let (values, indices) = condense(values, components);
+                (AttributeValues::U8(values), indices)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Attribute {
+    name: String,
+    components: usize,
+    values: AttributeValues
+}
+
+impl Attribute {
+    pub fn new(name: &str, components: usize, values: AttributeValues) -> Option<Attribute> {
+        if !(1..=4).contains(&components) || !values.len().is_multiple_of(components) {
+            return None;
+        }
+
+        if let AttributeValues::F32(ref values) = values {
+            if values.iter().any(|value| !value.is_finite()) {
+                return None;
+            }
+        }
+
+        Some(
+            Attribute {
+                name: name.to_owned(),
+                components,
+                values
+            }
+        )
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    pub fn values(&self) -> &AttributeValues {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len() / self.components
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.len() == 0
+    }
+
+    pub(super) fn gather<I: Iterator<Item = usize>>(&self, elements: I) -> Attribute {
+        Attribute {
+            name: self.name.clone(),
+            components: self.components,
+            values: self.values.gather(self.components, elements)
+        }
+    }
+
+    // Appends `other`'s values, which must have the same name, components and type.
+    pub(super) fn concat(&self, other: &Attribute) -> Option<Attribute> {
+        if self.name != other.name || self.components != other.components {
+            return None;
+        }
+
+        Some(
+            Attribute {
+                name: self.name.clone(),
+                components: self.components,
+                values: self.values.concat(&other.values)?
+            }
+        )
+    }
+
+    pub(super) fn condense(&self) -> (Attribute, Vec<usize>) {
+        let (values, indices) = self.values.condense(self.components);
+
+        (
+            Attribute {
+                name: self.name.clone(),
+                components: self.components,
+                values
+            },
+            indices
+        )
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AttributeIndices {
+    Vertices,
+    Uvs,
+    Own(Vec<usize>)
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AttributeChannel {
+    attribute: Attribute,
+    indices: AttributeIndices
+}
+
+impl AttributeChannel {
+    pub(super) fn new(attribute: Attribute, indices: AttributeIndices) -> AttributeChannel {
+        AttributeChannel {
+            attribute,
+            indices
+        }
+    }
+
+    pub fn attribute(&self) -> &Attribute {
+        &self.attribute
+    }
+
+    pub fn indices(&self) -> &AttributeIndices {
+        &self.indices
+    }
+
+    pub fn index(&self, corner: usize, (i, j): (usize, usize)) -> usize {
+        match self.indices {
+            AttributeIndices::Vertices => i,
+            AttributeIndices::Uvs => j,
+            AttributeIndices::Own(ref indices) => indices[corner]
+        }
+    }
+
+    pub(super) fn reorder_corners(&self, corners: &[usize]) -> AttributeChannel {
+        let indices = match self.indices {
+            AttributeIndices::Own(ref indices) => {
+                AttributeIndices::Own(corners.iter().map(|&corner| indices[corner]).collect())
+            }
+            ref indices => indices.clone()
+        };
+
+        AttributeChannel::new(self.attribute.clone(), indices)
+    }
+
+    // Follows the new position and UV orders, or orders its own values by first use.
+    pub(super) fn reorder_values(&self, vertices: &[usize], uvs: &[usize]) -> AttributeChannel {
+        match self.indices {
+            AttributeIndices::Vertices => {
+                AttributeChannel::new(self.attribute.gather(vertices.iter().cloned()), AttributeIndices::Vertices)
+            }
+            AttributeIndices::Uvs => {
+                AttributeChannel::new(self.attribute.gather(uvs.iter().cloned()), AttributeIndices::Uvs)
+            }
+            AttributeIndices::Own(ref indices) => {
+                let (order, remap) = first_use(indices.iter().cloned(), self.attribute.len());
+
+                AttributeChannel::new(
+                    self.attribute.gather(order.into_iter()),
+                    AttributeIndices::Own(indices.iter().map(|&i| remap[i]).collect())
+                )
+            }
+        }
+    }
+
+    // Keeps the leading values, as many as there are positions or UVs, or as its own indices use.
+    pub(super) fn truncate(&self, vertices: usize, uvs: usize) -> AttributeChannel {
+        let len = match self.indices {
+            AttributeIndices::Vertices => vertices,
+            AttributeIndices::Uvs => uvs,
+            AttributeIndices::Own(ref indices) => indices.iter().max().map_or(0, |&i| i + 1)
+        };
+
+        AttributeChannel::new(self.attribute.gather(0..len), self.indices.clone())
+    }
+}
+
+fn gather<T: Copy, I: Iterator<Item = usize>>(values: &[T], components: usize, elements: I) -> Vec<T> {
+    let mut gathered = vec![];
+
+    for element in elements {
+        gathered.extend_from_slice(&values[element * components..(element + 1) * components]);
+    }
+
+    gathered
+}
+
+fn condense<T: Copy + PartialOrd>(values: &[T], components: usize) -> (Vec<T>, Vec<usize>) {
+    let element = |i: usize| &values[i * components..(i + 1) * components];
+    let compare = |a: &usize, b: &usize| {
+        element(*a).partial_cmp(element(*b)).unwrap_or(Ordering::Equal)
+    };
+
+    let mut order: Vec<_> = (0..values.len() / components).collect();
+    order.sort_unstable_by(&compare);
+
+    let mut condensed: Vec<T> = vec![];
+    let mut indices = vec![0; order.len()];
+
+    for (k, &i) in order.iter().enumerate() {
+        if k == 0 || compare(&order[k - 1], &i) != Ordering::Equal {
+            condensed.extend_from_slice(element(i));
+        }
+
+        indices[i] = condensed.len() / components - 1;
+    }
+
+    (condensed, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_attribute_invalid() {
+        assert!(Attribute::new("color", 0, AttributeValues::U8(vec![])).is_none());
+        assert!(Attribute::new("color", 5, AttributeValues::U8(vec![0; 5])).is_none());
+        assert!(Attribute::new("color", 4, AttributeValues::U8(vec![0; 6])).is_none());
+        assert!(Attribute::new("weight", 1, AttributeValues::F32(vec![f32::NAN])).is_none());
+    }
+
+    #[test]
+    fn attribute_getters() {
+        let attribute = Attribute::new("joints", 4, AttributeValues::U16(vec![1; 8])).unwrap();
+
+        assert_eq!(attribute.name(), "joints");
+        assert_eq!(attribute.components(), 4);
+        assert_eq!(attribute.values(), &AttributeValues::U16(vec![1; 8]));
+        assert_eq!(attribute.len(), 2);
+    }
+
+    #[test]
+    fn gather_condense() {
+        let attribute = Attribute::new(
+            "uv1",
+            2,
+            AttributeValues::F32(vec![0.5, 0.5, 0.0, 1.0])
+        ).unwrap();
+
+        let gathered = attribute.gather(vec![1, 0, 1, 1].into_iter());
+
+        assert_eq!(
+            gathered.values(),
+            &AttributeValues::F32(vec![0.0, 1.0, 0.5, 0.5, 0.0, 1.0, 0.0, 1.0])
+        );
+
+        let (condensed, indices) = gathered.condense();
+
+        assert_eq!(condensed.values(), &AttributeValues::F32(vec![0.0, 1.0, 0.5, 0.5]));
+        assert_eq!(indices, vec![0, 1, 0, 0]);
+    }
+}
@@ -1,11 +1,84 @@
+use std::any::TypeId;
+
+use bincode;
+
+use super::bvh::{BlockBvh, Bvh};
 use super::geometry::GeometryData;
-use super::object::BlockObject;
+use super::meshlet::{BlockMeshlets, Meshlets};
+use super::object::{BlockObject, Object};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Block(pub(super) BlockInner);
 
+impl Block {
+    pub(super) fn kind(&self) -> BlockKind {
+        match self.0 {
+            BlockInner::GeometryData(_) => BlockKind::GeometryData,
+            BlockInner::Object(_) => BlockKind::Object,
+            BlockInner::Bvh(_) => BlockKind::Bvh,
+            BlockInner::Meshlets(_) => BlockKind::Meshlets
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub(super) enum BlockInner {
     GeometryData(GeometryData),
-    Object(BlockObject)
+    Object(BlockObject),
+    Bvh(BlockBvh),
+    Meshlets(BlockMeshlets)
+}
+
+// Variants must stay in the same order as in `BlockInner`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub(super) enum BlockKind {
+    GeometryData,
+    Object,
+    Bvh,
+    Meshlets
+}
+
+impl BlockKind {
+    // Reads only the variant tag of an encoded `Block`.
+    pub(super) fn peek(bytes: &[u8]) -> Option<BlockKind> {
+        bincode::deserialize(bytes).ok()
+    }
+
+    pub(super) fn type_id(&self) -> TypeId {
+        match *self {
+            BlockKind::GeometryData => TypeId::of::<GeometryData>(),
+            BlockKind::Object => TypeId::of::<Object>(),
+            BlockKind::Bvh => TypeId::of::<Bvh>(),
+            BlockKind::Meshlets => TypeId::of::<Meshlets>()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::geometry::Geometry;
+
+    #[test]
+    fn peek_kind() {
+        let block = Block(BlockInner::GeometryData(
+            GeometryData::Geometry(
+                Geometry::new(
+                    vec![(1.0, 2.0, 3.0); 10],
+                    vec![(0.0, 1.0); 6],
+                    vec![(1, 2); 18]
+                ).unwrap()
+            )
+        ));
+        let bytes = bincode::serialize(&block, bincode::Infinite).unwrap();
+
+        assert_eq!(BlockKind::peek(&bytes), Some(BlockKind::GeometryData));
+        assert_eq!(block.kind(), BlockKind::GeometryData);
+
+        let block = Block(BlockInner::Object(BlockObject { geometry_index: 3 }));
+        let bytes = bincode::serialize(&block, bincode::Infinite).unwrap();
+
+        assert_eq!(BlockKind::peek(&bytes), Some(BlockKind::Object));
+        assert_eq!(BlockKind::peek(&[]), None);
+    }
 }
@@ -0,0 +1,202 @@
+use super::math::{self, Vec3};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Aabb {
+    min: (f32, f32, f32),
+    max: (f32, f32, f32)
+}
+
+impl Aabb {
+    pub fn new(min: (f32, f32, f32), max: (f32, f32, f32)) -> Option<Aabb> {
+        if !(min.0 <= max.0 && min.1 <= max.1 && min.2 <= max.2) {
+            return None;
+        }
+
+        Some(Aabb { min, max })
+    }
+
+    pub fn from_points(points: &[(f32, f32, f32)]) -> Option<Aabb> {
+        let first = *points.first()?;
+
+        let (min, max) = points.iter().fold((first, first), |(min, max), &(x, y, z)| {
+            ((min.0.min(x), min.1.min(y), min.2.min(z)), (max.0.max(x), max.1.max(y), max.2.max(z)))
+        });
+
+        Aabb::new(min, max)
+    }
+
+    pub fn min(&self) -> (f32, f32, f32) {
+        self.min
+    }
+
+    pub fn max(&self) -> (f32, f32, f32) {
+        self.max
+    }
+
+    pub fn center(&self) -> (f32, f32, f32) {
+        math::scale(math::add(self.min, self.max), 0.5)
+    }
+
+    pub fn extents(&self) -> (f32, f32, f32) {
+        math::scale(math::sub(self.max, self.min), 0.5)
+    }
+
+    pub fn contains(&self, (x, y, z): (f32, f32, f32)) -> bool {
+        self.min.0 <= x && x <= self.max.0 &&
+            self.min.1 <= y && y <= self.max.1 &&
+            self.min.2 <= z && z <= self.max.2
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.0 <= other.max.0 && other.min.0 <= self.max.0 &&
+            self.min.1 <= other.max.1 && other.min.1 <= self.max.1 &&
+            self.min.2 <= other.max.2 && other.min.2 <= self.max.2
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1), self.min.2.min(other.min.2)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1), self.max.2.max(other.max.2))
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Sphere {
+    center: (f32, f32, f32),
+    radius: f32
+}
+
+impl Sphere {
+    pub fn new(center: (f32, f32, f32), radius: f32) -> Option<Sphere> {
+        if radius.is_nan() || radius < 0.0 {
+            return None;
+        }
+
+        Some(Sphere { center, radius })
+    }
+
+    // Ritter's approximation, within a few percent of the minimal sphere. The radius is measured
+    // again at the end so that rounding never leaves a point outside.
+    pub fn from_points(points: &[(f32, f32, f32)]) -> Option<Sphere> {
+        let first = *points.first()?;
+        let farthest = |from: Vec3| {
+            points.iter().cloned().fold(from, |best, p| {
+                if math::length(math::sub(p, from)) > math::length(math::sub(best, from)) { p } else { best }
+            })
+        };
+
+        let a = farthest(first);
+        let b = farthest(a);
+
+        let mut center = math::scale(math::add(a, b), 0.5);
+        let mut radius = math::length(math::sub(b, a)) / 2.0;
+
+        for &p in points {
+            let distance = math::length(math::sub(p, center));
+
+            if distance > radius {
+                let grown = (radius + distance) / 2.0;
+                center = math::add(center, math::scale(math::sub(p, center), (grown - radius) / distance));
+                radius = grown;
+            }
+        }
+
+        let radius = points.iter().map(|&p| math::length(math::sub(p, center))).fold(0.0, f32::max);
+
+        Sphere::new(center, radius)
+    }
+
+    pub fn center(&self) -> (f32, f32, f32) {
+        self.center
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn contains(&self, point: (f32, f32, f32)) -> bool {
+        math::length(math::sub(point, self.center)) <= self.radius
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Bounds {
+    aabb: Aabb,
+    sphere: Sphere
+}
+
+impl Bounds {
+    pub fn from_points(points: &[(f32, f32, f32)]) -> Option<Bounds> {
+        Some(
+            Bounds {
+                aabb: Aabb::from_points(points)?,
+                sphere: Sphere::from_points(points)?
+            }
+        )
+    }
+
+    pub fn aabb(&self) -> &Aabb {
+        &self.aabb
+    }
+
+    pub fn sphere(&self) -> &Sphere {
+        &self.sphere
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<(f32, f32, f32)> {
+        let mut state = 7u32;
+
+        (0..200).map(|_| {
+            let mut next = || {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 8) as f32 / (1 << 24) as f32 * 4.0 - 1.0
+            };
+
+            (next(), next() * 0.5, next() + 2.0)
+        }).collect()
+    }
+
+    #[test]
+    fn aabb() {
+        let aabb = Aabb::from_points(&[(1.0, -1.0, 0.0), (-2.0, 3.0, 0.5)]).unwrap();
+
+        assert_eq!(aabb.min(), (-2.0, -1.0, 0.0));
+        assert_eq!(aabb.max(), (1.0, 3.0, 0.5));
+        assert_eq!(aabb.center(), (-0.5, 1.0, 0.25));
+        assert!(aabb.contains((0.0, 0.0, 0.25)));
+        assert!(!aabb.contains((0.0, 0.0, 1.0)));
+
+        let other = Aabb::new((1.0, 3.0, 0.5), (2.0, 4.0, 1.0)).unwrap();
+
+        assert!(aabb.intersects(&other));
+        assert_eq!(aabb.union(&other).max(), (2.0, 4.0, 1.0));
+        assert!(Aabb::new((1.0, 0.0, 0.0), (0.0, 1.0, 1.0)).is_none());
+        assert!(Aabb::from_points(&[]).is_none());
+    }
+
+    #[test]
+    fn sphere() {
+        let points = points();
+        let sphere = Sphere::from_points(&points).unwrap();
+
+        assert!(points.iter().all(|&p| sphere.contains(p)));
+
+        // The two farthest apart points give a lower bound on the minimal radius.
+        let diameter = points.iter().flat_map(|&a| points.iter().map(move |&b| math::length(math::sub(a, b))))
+            .fold(0.0, f32::max);
+
+        assert!(sphere.radius() >= diameter / 2.0);
+        assert!(sphere.radius() < diameter * 0.7);
+
+        let single = Sphere::from_points(&[(1.0, 2.0, 3.0)]).unwrap();
+
+        assert_eq!(single.center(), (1.0, 2.0, 3.0));
+        assert_eq!(single.radius(), 0.0);
+    }
+}
@@ -0,0 +1,365 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use bincode;
+
+use super::yarn::Yarn;
+
+const MAGIC: &[u8] = b"bndl";
+// 1: the manifest's length, the manifest, then every entry's bytes.
+const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BundleEntry {
+    name: String,
+    kind: String,
+    offset: u64,
+    size: u64,
+    hash: u64,
+    dependencies: Vec<String>
+}
+
+impl BundleEntry {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn dependencies(&self) -> &[String] {
+        &self.dependencies[..]
+    }
+}
+
+#[derive(Debug)]
+pub enum BundleError {
+    Missing,
+    Io(io::Error),
+    // The entry reaches past the end of the bundle.
+    Truncated,
+    Corrupted,
+    InvalidYarn
+}
+
+// Both hand the yarn back, so that nothing tied is lost.
+#[derive(Debug)]
+pub enum InsertError {
+    DuplicateName(Yarn),
+    Serialize(Yarn)
+}
+
+impl InsertError {
+    pub fn into_yarn(self) -> Yarn {
+        match self {
+            InsertError::DuplicateName(yarn) | InsertError::Serialize(yarn) => yarn
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Bundle {
+    entries: Vec<BundleEntry>,
+    data: Vec<u8>
+}
+
+impl Bundle {
+    pub fn new() -> Bundle {
+        Bundle {
+            entries: vec![],
+            data: vec![]
+        }
+    }
+
+    pub fn entries(&self) -> &[BundleEntry] {
+        &self.entries[..]
+    }
+
+    pub fn insert(
+        &mut self,
+        name: &str,
+        kind: &str,
+        yarn: Yarn,
+        dependencies: Vec<String>
+    ) -> Result<(), InsertError> {
+        if self.entries.iter().any(|entry| entry.name == name) {
+            return Err(InsertError::DuplicateName(yarn));
+        }
+
+        let bytes = yarn.into_bytes().map_err(InsertError::Serialize)?;
+
+        self.entries.push(
+            BundleEntry {
+                name: name.to_owned(),
+                kind: kind.to_owned(),
+                offset: self.data.len() as u64,
+                size: bytes.len() as u64,
+                hash: hash(&bytes),
+                dependencies
+            }
+        );
+        self.data.extend(bytes);
+
+        Ok(())
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+
+        bytes.extend(bincode::serialize(&VERSION, bincode::Infinite).unwrap());
+
+        let manifest = bincode::serialize(&self.entries, bincode::Infinite).unwrap();
+
+        bytes.extend(bincode::serialize(&(manifest.len() as u64), bincode::Infinite).unwrap());
+        bytes.extend(manifest);
+        bytes.extend(self.data);
+
+        bytes
+    }
+}
+
+impl Default for Bundle {
+    fn default() -> Bundle {
+        Bundle::new()
+    }
+}
+
+#[derive(Debug)]
+pub struct BundleReader<R> {
+    reader: R,
+    entries: Vec<BundleEntry>,
+    data_offset: u64,
+    len: u64
+}
+
+impl<R: Read + Seek> BundleReader<R> {
+    pub fn new(mut reader: R) -> Option<BundleReader<R>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).ok()?;
+
+        if magic != MAGIC {
+            return None;
+        }
+
+        let version: u32 = bincode::deserialize_from(&mut reader, bincode::Bounded(4)).ok()?;
+
+        if version != VERSION {
+            return None;
+        }
+
+        let len: u64 = bincode::deserialize_from(&mut reader, bincode::Bounded(8)).ok()?;
+        let entries: Vec<BundleEntry> =
+            bincode::deserialize_from(&mut reader, bincode::Bounded(len)).ok()?;
+
+        let data_offset = 4 + 4 + 8 + len;
+        let len = reader.seek(SeekFrom::End(0)).ok()?;
+
+        Some(
+            BundleReader {
+                reader,
+                entries,
+                data_offset,
+                len
+            }
+        )
+    }
+
+    pub fn entries(&self) -> &[BundleEntry] {
+        &self.entries[..]
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&BundleEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    // Sizes are checked against the length of the bundle before anything is allocated for them.
+    pub fn read_bytes(&mut self, name: &str) -> Result<Vec<u8>, BundleError> {
+        let (offset, size, expected) = {
+            let entry = self.entry(name).ok_or(BundleError::Missing)?;
+            (entry.offset, entry.size, entry.hash)
+        };
+
+        let start = self.data_offset.checked_add(offset).ok_or(BundleError::Truncated)?;
+
+        match start.checked_add(size) {
+            Some(end) if end <= self.len => {}
+            _ => return Err(BundleError::Truncated)
+        }
+
+        self.reader.seek(SeekFrom::Start(start)).map_err(BundleError::Io)?;
+
+        let mut bytes = vec![0; size as usize];
+        self.reader.read_exact(&mut bytes).map_err(BundleError::Io)?;
+
+        if hash(&bytes) != expected {
+            return Err(BundleError::Corrupted);
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn read_yarn(&mut self, name: &str) -> Result<Yarn, BundleError> {
+        let bytes = self.read_bytes(name)?;
+        Yarn::from_bytes(&bytes).ok_or(BundleError::InvalidYarn)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+// 64-bit FNV-1a; stable across platforms and releases, unlike `DefaultHasher`.
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use super::*;
+    use super::super::geometry::{Geometry, GeometryData};
+    use super::super::object::Object;
+    use super::super::yarn::Tie;
+
+    fn yarn(x: f32) -> Yarn {
+        let geometry = Rc::new(
+            GeometryData::Geometry(
+                Geometry::new(
+                    vec![(x, 2.0, 3.0); 10],
+                    vec![(0.0, 1.0); 6],
+                    vec![(1, 2); 18]
+                ).unwrap()
+            )
+        );
+
+        let mut yarn = Yarn::new();
+
+        Object::new(geometry).tie(&mut yarn);
+
+        yarn
+    }
+
+    #[test]
+    fn insert_read() {
+        let mut bundle = Bundle::new();
+
+        bundle.insert("level", "level", yarn(1.0), vec!["prop".to_owned()]).unwrap();
+        bundle.insert("prop", "prop", yarn(2.0), vec![]).unwrap();
+
+        let mut reader = BundleReader::new(Cursor::new(bundle.into_bytes())).unwrap();
+
+        assert_eq!(reader.entries().len(), 2);
+        assert_eq!(reader.entry("level").unwrap().kind(), "level");
+        assert_eq!(reader.entry("level").unwrap().dependencies(), &["prop".to_owned()]);
+
+        let mut yarn = reader.read_yarn("prop").unwrap();
+        let object = Object::untie(&mut yarn).unwrap();
+
+        match *object.geometry().unwrap() {
+            GeometryData::Geometry(ref geometry) => {
+                assert_eq!(geometry.vertices(), &[(2.0, 2.0, 3.0); 10]);
+            }
+            _ => unreachable!()
+        }
+
+        match reader.read_yarn("missing") {
+            Err(BundleError::Missing) => {}
+            result => panic!("{:?}", result)
+        }
+    }
+
+    #[test]
+    fn insert_duplicate() {
+        let mut bundle = Bundle::new();
+
+        assert!(bundle.insert("prop", "prop", yarn(1.0), vec![]).is_ok());
+
+        match bundle.insert("prop", "prop", yarn(2.0), vec![]) {
+            Err(InsertError::DuplicateName(yarn)) => assert_eq!(yarn.len_roots(), 1),
+            result => panic!("{:?}", result)
+        }
+
+        // The geometry is still shared, so the yarn cannot be written until it is let go.
+        let geometry = Rc::new(GeometryData::Geometry(Geometry::new(vec![], vec![], vec![]).unwrap()));
+        let mut entangled = Yarn::new();
+
+        Object::new(geometry.clone()).tie(&mut entangled);
+
+        let entangled = match bundle.insert("other", "prop", entangled, vec![]) {
+            Err(error @ InsertError::Serialize(_)) => error.into_yarn(),
+            result => panic!("{:?}", result)
+        };
+
+        drop(geometry);
+
+        assert!(bundle.insert("other", "prop", entangled, vec![]).is_ok());
+    }
+
+    #[test]
+    fn unknown_version() {
+        let mut bytes = Bundle::new().into_bytes();
+
+        assert!(BundleReader::new(Cursor::new(bytes.clone())).is_some());
+
+        bytes[4] = 2;
+
+        assert!(BundleReader::new(Cursor::new(bytes)).is_none());
+        assert!(BundleReader::new(Cursor::new(b"bndl".to_vec())).is_none());
+    }
+
+    #[test]
+    fn corrupted_entry() {
+        let mut bundle = Bundle::new();
+
+        bundle.insert("prop", "prop", yarn(1.0), vec![]).unwrap();
+
+        let mut bytes = bundle.into_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+
+        let mut reader = BundleReader::new(Cursor::new(bytes)).unwrap();
+
+        match reader.read_bytes("prop") {
+            Err(BundleError::Corrupted) => {}
+            result => panic!("{:?}", result)
+        }
+    }
+
+    #[test]
+    fn truncated_entry() {
+        let mut bundle = Bundle::default();
+
+        bundle.insert("prop", "prop", yarn(1.0), vec![]).unwrap();
+
+        let mut bytes = bundle.into_bytes();
+        let len = bytes.len();
+        bytes.truncate(len - 1);
+
+        let mut reader = BundleReader::new(Cursor::new(bytes)).unwrap();
+
+        match reader.read_bytes("prop") {
+            Err(BundleError::Truncated) => {}
+            result => panic!("{:?}", result)
+        }
+
+        // A manifest claiming an entry far larger than the bundle is refused before allocating.
+        reader.entries[0].size = u64::MAX / 2;
+
+        match reader.read_bytes("prop") {
+            Err(BundleError::Truncated) => {}
+            result => panic!("{:?}", result)
+        }
+    }
+}
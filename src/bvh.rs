@@ -0,0 +1,819 @@
+use std::rc::Rc;
+
+use super::block::{Block, BlockInner};
+use super::bounds::Aabb;
+use super::geometry::{is_finite3, Geometry, GeometryData, Topology};
+use super::lazy::Lazy;
+use super::math::{self, Vec3};
+use super::yarn::{Tie, Yarn};
+
+const BINS: usize = 12;
+const MAX_LEAF: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    origin: (f32, f32, f32),
+    direction: (f32, f32, f32)
+}
+
+impl Ray {
+    // The direction is normalized, so hit distances are in object space units.
+    pub fn new(origin: (f32, f32, f32), direction: (f32, f32, f32)) -> Option<Ray> {
+        let length = math::length(direction);
+
+        if !is_finite3(&origin) || !length.is_finite() || length <= 0.0 {
+            return None;
+        }
+
+        Some(Ray { origin, direction: math::scale(direction, 1.0 / length) })
+    }
+
+    pub fn origin(&self) -> (f32, f32, f32) {
+        self.origin
+    }
+
+    pub fn direction(&self) -> (f32, f32, f32) {
+        self.direction
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    triangle: [usize; 3],
+    distance: f32,
+    barycentric: (f32, f32)
+}
+
+impl RayHit {
+    // The corners of the triangle that was hit.
+    pub fn triangle(&self) -> [usize; 3] {
+        self.triangle
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    // Weights of the triangle's second and third corners at the hit point.
+    pub fn barycentric(&self) -> (f32, f32) {
+        self.barycentric
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClosestPoint {
+    triangle: [usize; 3],
+    point: (f32, f32, f32),
+    distance: f32
+}
+
+impl ClosestPoint {
+    pub fn triangle(&self) -> [usize; 3] {
+        self.triangle
+    }
+
+    pub fn point(&self) -> (f32, f32, f32) {
+        self.point
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub(super) struct BvhNode {
+    aabb: Aabb,
+    // Leaves hold `count` triangles from `start`, inner nodes have `count` 0 and their children
+    // at `start` and `start + 1`.
+    start: usize,
+    count: usize
+}
+
+#[derive(Debug)]
+pub struct Bvh {
+    geometry: Lazy<GeometryData>,
+    nodes: Vec<BvhNode>,
+    triangles: Vec<[usize; 3]>,
+    // One past the largest corner any triangle uses, checked against the geometry once it loads.
+    corners: usize
+}
+
+impl Bvh {
+    // Only uncompressed triangle geometry can be queried without decoding it again on every query.
+    pub fn new(geometry: Rc<GeometryData>) -> Option<Bvh> {
+        let (nodes, triangles) = match *geometry {
+            GeometryData::Geometry(ref geometry) => build(triangle_geometry(geometry)?),
+            _ => return None
+        };
+
+        Some(
+            Bvh {
+                geometry: Lazy::new(geometry),
+                nodes,
+                corners: corners(&triangles),
+                triangles
+            }
+        )
+    }
+
+    pub fn is_geometry_loaded(&self) -> bool {
+        self.geometry.is_loaded()
+    }
+
+    // `None` when the geometry block could not be decoded.
+    pub fn geometry(&self) -> Option<&GeometryData> {
+        self.geometry.get()
+    }
+
+    pub fn len_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn aabb(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.aabb)
+    }
+
+    // Triangles are hit from both sides.
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<RayHit> {
+        let geometry = self.loaded()?;
+        let inverse = (1.0 / ray.direction.0, 1.0 / ray.direction.1, 1.0 / ray.direction.2);
+        let entry = |node: usize| ray_aabb(ray.origin, inverse, &self.nodes[node].aabb);
+
+        let mut limit = max_distance;
+        let mut hit = None;
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(index) = stack.pop() {
+            match entry(index) {
+                Some(distance) if distance <= limit => {}
+                _ => continue
+            }
+
+            let node = self.nodes[index];
+
+            if node.count > 0 {
+                for &triangle in &self.triangles[node.start..node.start + node.count] {
+                    if let Some((distance, u, v)) = ray_triangle(ray, positions(geometry, triangle), limit) {
+                        limit = distance;
+                        hit = Some(RayHit { triangle, distance, barycentric: (u, v) });
+                    }
+                }
+            } else {
+                let near = entry(node.start).unwrap_or(f32::INFINITY);
+                let far = entry(node.start + 1).unwrap_or(f32::INFINITY);
+
+                // The nearer child is visited first so that it can shorten the farther one's search.
+                if near <= far {
+                    stack.push(node.start + 1);
+                    stack.push(node.start);
+                } else {
+                    stack.push(node.start);
+                    stack.push(node.start + 1);
+                }
+            }
+        }
+
+        hit
+    }
+
+    pub fn closest_point(&self, point: (f32, f32, f32)) -> Option<ClosestPoint> {
+        let geometry = self.loaded()?;
+
+        if !is_finite3(&point) {
+            return None;
+        }
+
+        let mut best_squared = f32::INFINITY;
+        let mut closest = None;
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+
+            if aabb_distance_squared(&node.aabb, point) > best_squared {
+                continue;
+            }
+
+            if node.count > 0 {
+                for &triangle in &self.triangles[node.start..node.start + node.count] {
+                    let candidate = point_triangle(point, positions(geometry, triangle));
+                    let offset = math::sub(candidate, point);
+                    let squared = math::dot(offset, offset);
+
+                    if squared < best_squared {
+                        best_squared = squared;
+                        closest = Some(ClosestPoint { triangle, point: candidate, distance: squared.sqrt() });
+                    }
+                }
+            } else {
+                let near = aabb_distance_squared(&self.nodes[node.start].aabb, point);
+                let far = aabb_distance_squared(&self.nodes[node.start + 1].aabb, point);
+
+                if near <= far {
+                    stack.push(node.start + 1);
+                    stack.push(node.start);
+                } else {
+                    stack.push(node.start);
+                    stack.push(node.start + 1);
+                }
+            }
+        }
+
+        closest
+    }
+
+    // Every triangle that touches the box, tested exactly rather than by its own bounds.
+    pub fn overlapping(&self, aabb: &Aabb) -> Vec<[usize; 3]> {
+        let geometry = match self.loaded() {
+            Some(geometry) => geometry,
+            None => return vec![]
+        };
+
+        let mut triangles = vec![];
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+
+            if !node.aabb.intersects(aabb) {
+                continue;
+            }
+
+            if node.count > 0 {
+                triangles.extend(self.triangles[node.start..node.start + node.count].iter().filter(|&&triangle| {
+                    triangle_aabb(positions(geometry, triangle), aabb)
+                }));
+            } else {
+                stack.push(node.start + 1);
+                stack.push(node.start);
+            }
+        }
+
+        triangles
+    }
+
+    // `None` as well when the triangles do not fit the geometry, which only a corrupt block causes.
+    fn loaded(&self) -> Option<&Geometry> {
+        match *self.geometry.get()? {
+            GeometryData::Geometry(ref geometry) if self.corners <= geometry.indices().len() => Some(geometry),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct BlockBvh {
+    pub(super) geometry_index: usize,
+    pub(super) nodes: Vec<BvhNode>,
+    pub(super) triangles: Vec<[usize; 3]>
+}
+
+impl Tie for Bvh {
+    fn into_block(self, yarn: &mut Yarn) -> Block {
+        let geometry_index = yarn.tie_lazy(self.geometry);
+
+        Block(BlockInner::Bvh(
+            BlockBvh {
+                geometry_index,
+                nodes: self.nodes,
+                triangles: self.triangles
+            }
+        ))
+    }
+
+    fn from_block(block: Block, yarn: &mut Yarn) -> Option<Self> {
+        match block {
+            Block(BlockInner::Bvh(BlockBvh { geometry_index, nodes, triangles })) => {
+                if !is_valid_tree(&nodes, triangles.len()) {
+                    return None;
+                }
+
+                Some(
+                    Bvh {
+                        geometry: yarn.untie_lazy(geometry_index)?,
+                        nodes,
+                        corners: corners(&triangles),
+                        triangles
+                    }
+                )
+            }
+            _ => unreachable!()
+        }
+    }
+}
+
+fn triangle_geometry(geometry: &Geometry) -> Option<&Geometry> {
+    match geometry.topology() {
+        Topology::TriangleList | Topology::TriangleStrip => Some(geometry),
+        _ => None
+    }
+}
+
+// Inner nodes' children must come after them, so that traversal always ends, and leaves must stay
+// within the triangles.
+fn is_valid_tree(nodes: &[BvhNode], triangles: usize) -> bool {
+    nodes.iter().enumerate().all(|(index, node)| {
+        if node.count > 0 {
+            node.start.checked_add(node.count).is_some_and(|end| end <= triangles)
+        } else {
+            node.start > index && node.start < nodes.len() - 1
+        }
+    })
+}
+
+fn corners(triangles: &[[usize; 3]]) -> usize {
+    triangles.iter().flat_map(|triangle| triangle.iter()).map(|&corner| corner.saturating_add(1)).max().unwrap_or(0)
+}
+
+fn positions(geometry: &Geometry, triangle: [usize; 3]) -> [Vec3; 3] {
+    let position = |corner: usize| geometry.vertices()[geometry.indices()[corner].0];
+
+    [position(triangle[0]), position(triangle[1]), position(triangle[2])]
+}
+
+fn axis((x, y, z): Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => x,
+        1 => y,
+        _ => z
+    }
+}
+
+// Half the surface area, which is all the SAH needs.
+fn area(aabb: &Aabb) -> f32 {
+    let (x, y, z) = math::sub(aabb.max(), aabb.min());
+
+    x * y + y * z + z * x
+}
+
+fn union(aabbs: &[Option<Aabb>]) -> Option<Aabb> {
+    aabbs.iter().fold(None, |total, aabb| match (total, *aabb) {
+        (Some(total), Some(aabb)) => Some(total.union(&aabb)),
+        (total, aabb) => total.or(aabb)
+    })
+}
+
+#[derive(Clone, Copy)]
+struct Item {
+    triangle: [usize; 3],
+    aabb: Aabb,
+    centroid: Vec3
+}
+
+fn build(geometry: &Geometry) -> (Vec<BvhNode>, Vec<[usize; 3]>) {
+    let mut items: Vec<_> = geometry.triangles().into_iter().map(|triangle| {
+        let points = positions(geometry, triangle);
+        let aabb = Aabb::from_points(&points).unwrap();
+
+        Item { triangle, aabb, centroid: aabb.center() }
+    }).collect();
+
+    let mut nodes = vec![];
+    let mut stack = vec![];
+
+    if let Some(aabb) = union(&items.iter().map(|item| Some(item.aabb)).collect::<Vec<_>>()) {
+        nodes.push(BvhNode { aabb, start: 0, count: 0 });
+        stack.push((0, 0, items.len()));
+    }
+
+    while let Some((node, start, end)) = stack.pop() {
+        let aabb = nodes[node].aabb;
+
+        match split(&mut items[start..end], &aabb) {
+            Some((mid, left, right)) => {
+                let children = nodes.len();
+
+                nodes.push(BvhNode { aabb: left, start: 0, count: 0 });
+                nodes.push(BvhNode { aabb: right, start: 0, count: 0 });
+                nodes[node].start = children;
+
+                stack.push((children + 1, start + mid, end));
+                stack.push((children, start, start + mid));
+            }
+            None => {
+                nodes[node].start = start;
+                nodes[node].count = end - start;
+            }
+        }
+    }
+
+    (nodes, items.into_iter().map(|item| item.triangle).collect())
+}
+
+// Bins the centroids along each axis and partitions at the plane with the lowest surface area
+// heuristic cost, returning where the right half starts and both halves' bounds. Small nodes stay
+// leaves unless splitting is cheaper.
+fn split(items: &mut [Item], aabb: &Aabb) -> Option<(usize, Aabb, Aabb)> {
+    if items.len() <= 2 {
+        return None;
+    }
+
+    let centroids = Aabb::from_points(&items.iter().map(|item| item.centroid).collect::<Vec<_>>())?;
+    let bin = |item: &Item, a: usize| {
+        let min = axis(centroids.min(), a);
+        let extent = axis(centroids.max(), a) - min;
+        let t = (axis(item.centroid, a) - min) / extent * BINS as f32;
+
+        // NaN, from an extent too large to represent, falls into the first bin.
+        t.max(0.0).min((BINS - 1) as f32) as usize
+    };
+
+    let mut best: Option<(f32, usize, usize)> = None;
+
+    for a in 0..3 {
+        if axis(centroids.max(), a) <= axis(centroids.min(), a) {
+            continue;
+        }
+
+        let mut bins = [(None, 0); BINS];
+
+        for item in items.iter() {
+            let b = bin(item, a);
+            bins[b].0 = union(&[bins[b].0, Some(item.aabb)]);
+            bins[b].1 += 1;
+        }
+
+        for s in 1..BINS {
+            let (left, right) = bins.split_at(s);
+            let count = |bins: &[(Option<Aabb>, usize)]| bins.iter().map(|bin| bin.1).sum::<usize>();
+            let bounds = |bins: &[(Option<Aabb>, usize)]| union(&bins.iter().map(|bin| bin.0).collect::<Vec<_>>());
+
+            if let (Some(l), Some(r)) = (bounds(left), bounds(right)) {
+                let cost = area(&l) * count(left) as f32 + area(&r) * count(right) as f32;
+
+                if best.is_none_or(|(best, _, _)| cost < best) {
+                    best = Some((cost, a, s));
+                }
+            }
+        }
+    }
+
+    let (cost, a, s) = best?;
+
+    // Costs are scaled by the node's area, with traversal and intersection costing the same.
+    if cost + area(aabb) >= items.len() as f32 * area(aabb) && items.len() <= MAX_LEAF {
+        return None;
+    }
+
+    let (left, right): (Vec<_>, Vec<_>) = items.iter().cloned().partition(|item| bin(item, a) < s);
+    let mid = left.len();
+
+    for (slot, item) in items.iter_mut().zip(left.into_iter().chain(right)) {
+        *slot = item;
+    }
+
+    let bounds = |items: &[Item]| union(&items.iter().map(|item| Some(item.aabb)).collect::<Vec<_>>()).unwrap();
+
+    Some((mid, bounds(&items[..mid]), bounds(&items[mid..])))
+}
+
+// Distance along the ray at which it enters the box, if it does at all.
+fn ray_aabb(origin: Vec3, inverse: Vec3, aabb: &Aabb) -> Option<f32> {
+    let (near, far) = (0..3).fold((0.0f32, f32::INFINITY), |(near, far), a| {
+        let t1 = (axis(aabb.min(), a) - axis(origin, a)) * axis(inverse, a);
+        let t2 = (axis(aabb.max(), a) - axis(origin, a)) * axis(inverse, a);
+
+        (near.max(t1.min(t2)), far.min(t1.max(t2)))
+    });
+
+    if near <= far {
+        Some(near)
+    } else {
+        None
+    }
+}
+
+// Möller-Trumbore, returning the distance and barycentric coordinates of the hit.
+fn ray_triangle(ray: &Ray, triangle: [Vec3; 3], limit: f32) -> Option<(f32, f32, f32)> {
+    let e1 = math::sub(triangle[1], triangle[0]);
+    let e2 = math::sub(triangle[2], triangle[0]);
+    let p = math::cross(ray.direction, e2);
+    let determinant = math::dot(e1, p);
+
+    if determinant == 0.0 || !determinant.is_finite() {
+        return None;
+    }
+
+    let s = math::sub(ray.origin, triangle[0]);
+    let u = math::dot(s, p) / determinant;
+
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = math::cross(s, e1);
+    let v = math::dot(ray.direction, q) / determinant;
+
+    if !(v >= 0.0 && u + v <= 1.0) {
+        return None;
+    }
+
+    let distance = math::dot(e2, q) / determinant;
+
+    if distance >= 0.0 && distance <= limit {
+        Some((distance, u, v))
+    } else {
+        None
+    }
+}
+
+fn aabb_distance_squared(aabb: &Aabb, point: Vec3) -> f32 {
+    (0..3).map(|a| {
+        let p = axis(point, a);
+        let d = (axis(aabb.min(), a) - p).max(p - axis(aabb.max(), a)).max(0.0);
+
+        d * d
+    }).sum()
+}
+
+// Ericson's region test from Real-Time Collision Detection.
+fn point_triangle(p: Vec3, triangle: [Vec3; 3]) -> Vec3 {
+    let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+    let ab = math::sub(b, a);
+    let ac = math::sub(c, a);
+
+    let ap = math::sub(p, a);
+    let d1 = math::dot(ab, ap);
+    let d2 = math::dot(ac, ap);
+
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = math::sub(p, b);
+    let d3 = math::dot(ab, bp);
+    let d4 = math::dot(ac, bp);
+
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return math::add(a, math::scale(ab, d1 / (d1 - d3)));
+    }
+
+    let cp = math::sub(p, c);
+    let d5 = math::dot(ab, cp);
+    let d6 = math::dot(ac, cp);
+
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return math::add(a, math::scale(ac, d2 / (d2 - d6)));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return math::add(b, math::scale(math::sub(c, b), (d4 - d3) / ((d4 - d3) + (d5 - d6))));
+    }
+
+    let denominator = va + vb + vc;
+    let inside = math::add(a, math::add(math::scale(ab, vb / denominator), math::scale(ac, vc / denominator)));
+
+    // Only degenerate triangles get here with a zero denominator.
+    if is_finite3(&inside) {
+        inside
+    } else {
+        a
+    }
+}
+
+// Separating axis test over the box's axes, the triangle's normal and their edges' cross products.
+fn triangle_aabb(triangle: [Vec3; 3], aabb: &Aabb) -> bool {
+    let center = aabb.center();
+    let extents = aabb.extents();
+    let v = [
+        math::sub(triangle[0], center),
+        math::sub(triangle[1], center),
+        math::sub(triangle[2], center)
+    ];
+    let edges = [math::sub(v[1], v[0]), math::sub(v[2], v[1]), math::sub(v[0], v[2])];
+    let units = [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)];
+
+    let mut axes = units.to_vec();
+    axes.push(math::cross(edges[0], edges[1]));
+
+    for &edge in &edges {
+        for &unit in &units {
+            axes.push(math::cross(unit, edge));
+        }
+    }
+
+    axes.into_iter().all(|a| {
+        let projections: Vec<_> = v.iter().map(|&v| math::dot(v, a)).collect();
+        let min = projections.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = projections.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let radius = extents.0 * a.0.abs() + extents.1 * a.1.abs() + extents.2 * a.2.abs();
+
+        min <= radius && max >= -radius
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use super::super::object::Object;
+    use super::super::yarn::{Tie, Yarn};
+
+    fn terrain() -> Geometry {
+        let n = 24;
+        let vertices = (0..(n + 1) * (n + 1)).map(|i| {
+            let (x, y) = ((i % (n + 1)) as f32, (i / (n + 1)) as f32);
+            (x, y, (x * 0.7).sin() * (y * 0.4).cos() * 2.0)
+        }).collect();
+        let indices = (0..n * n).flat_map(|q| {
+            let v = q / n * (n + 1) + q % n;
+            vec![v, v + 1, v + n + 2, v, v + n + 2, v + n + 1]
+        }).map(|v| (v, 0)).collect();
+
+        Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap()
+    }
+
+    fn random(state: &mut u32) -> f32 {
+        *state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn brute_raycast(geometry: &Geometry, ray: &Ray) -> Option<f32> {
+        geometry.triangles().into_iter()
+            .filter_map(|triangle| ray_triangle(ray, positions(geometry, triangle), f32::INFINITY))
+            .map(|(distance, _, _)| distance)
+            .fold(None, |best: Option<f32>, distance| Some(best.map_or(distance, |best| best.min(distance))))
+    }
+
+    #[test]
+    fn raycast() {
+        let geometry = Rc::new(GeometryData::Geometry(terrain()));
+        let bvh = Bvh::new(geometry.clone()).unwrap();
+        let geometry = match *geometry {
+            GeometryData::Geometry(ref geometry) => geometry,
+            _ => unreachable!()
+        };
+        let mut state = 3;
+
+        assert!(bvh.len_nodes() > 1);
+
+        for _ in 0..200 {
+            let origin = (random(&mut state) * 30.0 - 3.0, random(&mut state) * 30.0 - 3.0, 6.0);
+            let target = (random(&mut state) * 24.0, random(&mut state) * 24.0, -3.0);
+            let ray = Ray::new(origin, math::sub(target, origin)).unwrap();
+
+            let hit = bvh.raycast(&ray, f32::INFINITY);
+
+            assert_eq!(hit.map(|hit| hit.distance()), brute_raycast(geometry, &ray));
+
+            if let Some(hit) = hit {
+                let points = positions(geometry, hit.triangle());
+                let (u, v) = hit.barycentric();
+                let on_triangle = math::add(
+                    points[0],
+                    math::add(math::scale(math::sub(points[1], points[0]), u), math::scale(math::sub(points[2], points[0]), v))
+                );
+                let along_ray = math::add(ray.origin(), math::scale(ray.direction(), hit.distance()));
+
+                assert!(math::length(math::sub(on_triangle, along_ray)) < 1e-3);
+                assert!(bvh.raycast(&ray, hit.distance() * 0.99).is_none());
+            }
+        }
+
+        assert!(Ray::new((0.0, 0.0, 0.0), (0.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn closest_point() {
+        let geometry = terrain();
+        let bvh = Bvh::new(Rc::new(GeometryData::Geometry(geometry.clone()))).unwrap();
+        let mut state = 5;
+
+        for _ in 0..100 {
+            let point = (random(&mut state) * 30.0 - 3.0, random(&mut state) * 30.0 - 3.0, random(&mut state) * 8.0 - 4.0);
+            let closest = bvh.closest_point(point).unwrap();
+
+            let brute = geometry.triangles().into_iter()
+                .map(|triangle| math::length(math::sub(point_triangle(point, positions(&geometry, triangle)), point)))
+                .fold(f32::INFINITY, f32::min);
+
+            assert!((closest.distance() - brute).abs() < 1e-4);
+            assert!((math::length(math::sub(closest.point(), point)) - closest.distance()).abs() < 1e-4);
+        }
+
+        let corner = bvh.closest_point((-1.0, -1.0, 0.0)).unwrap();
+
+        assert_eq!(corner.point(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn overlapping() {
+        let geometry = terrain();
+        let bvh = Bvh::new(Rc::new(GeometryData::Geometry(geometry.clone()))).unwrap();
+        let query = Aabb::new((3.5, 3.5, -10.0), (5.5, 4.5, 10.0)).unwrap();
+
+        let mut found = bvh.overlapping(&query);
+        let mut brute: Vec<_> = geometry.triangles().into_iter()
+            .filter(|&triangle| triangle_aabb(positions(&geometry, triangle), &query))
+            .collect();
+
+        found.sort();
+        brute.sort();
+
+        assert_eq!(found, brute);
+        assert_eq!(found.len(), 3 * 2 * 2);
+
+        // The box only touches the triangle's bounds, not the triangle itself.
+        let triangle = [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)];
+
+        assert!(!triangle_aabb(triangle, &Aabb::new((0.8, 0.8, -1.0), (1.0, 1.0, 1.0)).unwrap()));
+        assert!(bvh.overlapping(&Aabb::new((30.0, 30.0, 0.0), (31.0, 31.0, 0.0)).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn new_invalid() {
+        let lines = Geometry::with_topology(
+            Topology::LineList,
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0)]
+        ).unwrap();
+
+        assert!(Bvh::new(Rc::new(GeometryData::Geometry(lines))).is_none());
+        assert!(Bvh::new(Rc::new(GeometryData::GeometryCompressed(terrain().compress()))).is_none());
+
+        let empty = Bvh::new(Rc::new(GeometryData::Geometry(Geometry::new(vec![], vec![], vec![]).unwrap()))).unwrap();
+
+        assert!(empty.aabb().is_none());
+        assert!(empty.closest_point((0.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn tie_untie() {
+        let geometry = Rc::new(GeometryData::Geometry(terrain()));
+        let bvh = Bvh::new(geometry.clone()).unwrap();
+        let object = Object::new(geometry);
+
+        let mut yarn = Yarn::new();
+
+        bvh.tie(&mut yarn);
+        object.tie(&mut yarn);
+
+        assert_eq!(yarn.len_blocks(), 3);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+        let bvh = Bvh::untie(&mut yarn).unwrap();
+        let object = Object::untie(&mut yarn).unwrap();
+
+        assert!(!bvh.is_geometry_loaded());
+
+        let ray = Ray::new((10.5, 10.5, 10.0), (0.0, 0.0, -1.0)).unwrap();
+
+        assert!(bvh.raycast(&ray, f32::INFINITY).is_some());
+        assert!(object.is_geometry_loaded());
+        assert_eq!(bvh.geometry().unwrap() as *const GeometryData, object.geometry().unwrap() as *const GeometryData);
+    }
+
+    #[test]
+    fn untie_corrupt() {
+        let bvh = Bvh::new(Rc::new(GeometryData::Geometry(terrain()))).unwrap();
+        let block = |nodes: &[BvhNode], triangles: &[[usize; 3]], yarn: &mut Yarn| {
+            let geometry_index = yarn.tie_lazy(Lazy::new(Rc::new(GeometryData::Geometry(terrain()))));
+
+            Block(BlockInner::Bvh(
+                BlockBvh { geometry_index, nodes: nodes.to_vec(), triangles: triangles.to_vec() }
+            ))
+        };
+
+        let mut cyclic = bvh.nodes.clone();
+        cyclic[1] = BvhNode { count: 0, ..cyclic[0] };
+
+        let mut overflowing = bvh.nodes.clone();
+        let leaf = overflowing.iter().position(|node| node.count > 0).unwrap();
+        overflowing[leaf].count = usize::MAX;
+
+        for nodes in &[cyclic, overflowing] {
+            let mut yarn = Yarn::new();
+            let block = block(nodes, &bvh.triangles, &mut yarn);
+
+            assert!(Bvh::from_block(block, &mut yarn).is_none());
+        }
+
+        let mut triangles = bvh.triangles.clone();
+        triangles[0][2] = usize::MAX;
+
+        let mut yarn = Yarn::new();
+        let block = block(&bvh.nodes, &triangles, &mut yarn);
+        let corrupt = Bvh::from_block(block, &mut yarn).unwrap();
+        let ray = Ray::new((10.5, 10.5, 10.0), (0.0, 0.0, -1.0)).unwrap();
+
+        assert!(corrupt.raycast(&ray, f32::INFINITY).is_none());
+        assert!(corrupt.closest_point((0.0, 0.0, 0.0)).is_none());
+    }
+}
@@ -3,14 +3,19 @@ use std::io::Read;
 use bincode;
 use brotli2::read::{BrotliEncoder, BrotliDecoder};
 
+use super::attribute::{Attribute, AttributeChannel, AttributeIndices};
 use super::block::{Block, BlockInner};
-use super::yarn::{Tie, Yarn};
+use super::bounds::Bounds;
+use super::lod::GeometryLods;
+use super::weld::WeldOptions;
+use super::yarn::{Leaf, Tie, Yarn};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum GeometryData {
     Geometry(Geometry),
     GeometryCompressed(GeometryCompressed),
-    GeometryExpanded(GeometryExpanded)
+    GeometryExpanded(GeometryExpanded),
+    GeometryLods(GeometryLods)
 }
 
 impl Tie for GeometryData {
@@ -26,11 +31,65 @@ impl Tie for GeometryData {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl Leaf for GeometryData {}
+
+impl GeometryData {
+    // Empty geometry has no bounds. Level 0 bounds every coarser level of detail well enough.
+    pub fn bounds(&self) -> Option<Bounds> {
+        match *self {
+            GeometryData::Geometry(ref geometry) => geometry.bounds(),
+            GeometryData::GeometryCompressed(ref geometry) => geometry.bounds(),
+            GeometryData::GeometryExpanded(ref geometry) => geometry.bounds(),
+            GeometryData::GeometryLods(ref lods) => lods.levels().first()?.geometry().bounds()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Topology {
+    TriangleList,
+    TriangleStrip,
+    LineList,
+    LineStrip,
+    PointList
+}
+
+impl Topology {
+    pub fn is_valid_len(&self, len: usize) -> bool {
+        match *self {
+            Topology::TriangleList => len.is_multiple_of(3),
+            Topology::TriangleStrip => len == 0 || len >= 3,
+            Topology::LineList => len.is_multiple_of(2),
+            Topology::LineStrip => len != 1,
+            Topology::PointList => true
+        }
+    }
+
+    pub fn to_list(&self) -> Topology {
+        match *self {
+            Topology::TriangleList | Topology::TriangleStrip => Topology::TriangleList,
+            Topology::LineList | Topology::LineStrip => Topology::LineList,
+            Topology::PointList => Topology::PointList
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Channel<T> {
+    values: Vec<T>,
+    indices: Vec<usize>
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Geometry {
+    topology: Topology,
     vertices: Vec<(f32, f32, f32)>,
     uvs: Vec<(f32, f32)>,
-    indices: Vec<(usize, usize)>
+    indices: Vec<(usize, usize)>,
+    normals: Option<Channel<(f32, f32, f32)>>,
+    tangents: Option<Channel<(f32, f32, f32, f32)>>,
+    attributes: Vec<AttributeChannel>,
+    bounds: Option<Bounds>
 }
 
 impl Geometry {
@@ -39,27 +98,126 @@ impl Geometry {
         uvs: Vec<(f32, f32)>,
         indices: Vec<(usize, usize)>
     ) -> Option<Geometry> {
-        if vertices.iter().any(|&(x, y, z)| !x.is_finite() || !y.is_finite() || !z.is_finite()) {
+        Geometry::with_topology(Topology::TriangleList, vertices, uvs, indices)
+    }
+
+    pub fn with_topology(
+        topology: Topology,
+        vertices: Vec<(f32, f32, f32)>,
+        uvs: Vec<(f32, f32)>,
+        indices: Vec<(usize, usize)>
+    ) -> Option<Geometry> {
+        if !vertices.iter().all(is_finite3) || !uvs.iter().all(is_finite2) {
             return None;
         }
 
-        if uvs.iter().any(|&(u, v)| !u.is_finite() || !v.is_finite()) {
+        if indices.iter().any(|&(i, j)| i >= vertices.len() || j >= uvs.len()) {
             return None;
         }
 
-        if indices.iter().any(|&(i, j)| i >= vertices.len() || j >= uvs.len()) {
+        if !topology.is_valid_len(indices.len()) {
+            return None;
+        }
+
+        Some(Geometry::from_parts(topology, vertices, uvs, indices))
+    }
+
+    // Skips validation, for callers that derive every field from another geometry's.
+    pub(super) fn from_parts(
+        topology: Topology,
+        vertices: Vec<(f32, f32, f32)>,
+        uvs: Vec<(f32, f32)>,
+        indices: Vec<(usize, usize)>
+    ) -> Geometry {
+        Geometry {
+            topology,
+            bounds: Bounds::from_points(&vertices),
+            vertices,
+            uvs,
+            indices,
+            normals: None,
+            tangents: None,
+            attributes: vec![]
+        }
+    }
+
+    pub fn with_normals(
+        self,
+        normals: Vec<(f32, f32, f32)>,
+        indices: Vec<usize>
+    ) -> Option<Geometry> {
+        if !normals.iter().all(is_finite3) {
+            return None;
+        }
+
+        if indices.len() != self.indices.len() || indices.iter().any(|&i| i >= normals.len()) {
             return None;
         }
 
         Some(
             Geometry {
-                vertices,
-                uvs,
-                indices
+                normals: Some(Channel { values: normals, indices }),
+                ..self
             }
         )
     }
 
+    pub fn with_tangents(
+        self,
+        tangents: Vec<(f32, f32, f32, f32)>,
+        indices: Vec<usize>
+    ) -> Option<Geometry> {
+        if !tangents.iter().all(is_tangent) {
+            return None;
+        }
+
+        if indices.len() != self.indices.len() || indices.iter().any(|&i| i >= tangents.len()) {
+            return None;
+        }
+
+        Some(
+            Geometry {
+                tangents: Some(Channel { values: tangents, indices }),
+                ..self
+            }
+        )
+    }
+
+    pub fn with_attribute(
+        mut self,
+        attribute: Attribute,
+        indices: AttributeIndices
+    ) -> Option<Geometry> {
+        if self.attribute(attribute.name()).is_some() {
+            return None;
+        }
+
+        let valid = match indices {
+            AttributeIndices::Vertices => attribute.len() == self.vertices.len(),
+            AttributeIndices::Uvs => attribute.len() == self.uvs.len(),
+            AttributeIndices::Own(ref indices) => {
+                indices.len() == self.indices.len() &&
+                    indices.iter().all(|&i| i < attribute.len())
+            }
+        };
+
+        if !valid {
+            return None;
+        }
+
+        self.attributes.push(AttributeChannel::new(attribute, indices));
+
+        Some(self)
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
     pub fn vertices(&self) -> &[(f32, f32, f32)] {
         &self.vertices[..]
     }
@@ -72,6 +230,178 @@ impl Geometry {
         &self.indices[..]
     }
 
+    // Corners of every non-degenerate triangle, with strip winding already alternated.
+    pub(super) fn triangles(&self) -> Vec<[usize; 3]> {
+        match self.topology {
+            Topology::TriangleList => {
+                (0..self.indices.len() / 3).map(|t| [3 * t, 3 * t + 1, 3 * t + 2]).collect()
+            }
+            Topology::TriangleStrip => {
+                (0..self.indices.len().saturating_sub(2)).map(|i| {
+                    if i % 2 == 0 {
+                        [i, i + 1, i + 2]
+                    } else {
+                        [i + 1, i, i + 2]
+                    }
+                }).filter(|triangle| {
+                    let a = self.indices[triangle[0]].0;
+                    let b = self.indices[triangle[1]].0;
+                    let c = self.indices[triangle[2]].0;
+
+                    a != b && b != c && a != c
+                }).collect()
+            }
+            _ => vec![]
+        }
+    }
+
+    // The first triangle, and the position within it, that each corner belongs to.
+    pub(super) fn corner_owners(&self, triangles: &[[usize; 3]]) -> Vec<Option<(usize, usize)>> {
+        let mut owners = vec![None; self.indices.len()];
+
+        for (t, triangle) in triangles.iter().enumerate() {
+            for (i, &corner) in triangle.iter().enumerate() {
+                if owners[corner].is_none() {
+                    owners[corner] = Some((t, i));
+                }
+            }
+        }
+
+        owners
+    }
+
+    // Corners in the order the list version of this topology visits them.
+    pub(super) fn list_corners(&self) -> Vec<usize> {
+        match self.topology {
+            Topology::TriangleStrip => {
+                self.triangles().iter().flat_map(|triangle| triangle.iter().cloned()).collect()
+            }
+            Topology::LineStrip => {
+                (0..self.indices.len().saturating_sub(1)).flat_map(|i| vec![i, i + 1]).collect()
+            }
+            _ => (0..self.indices.len()).collect()
+        }
+    }
+
+    // Moves every per-corner index so that corner `k` takes the old corner `corners[k]`.
+    pub(super) fn reorder_corners(&mut self, corners: &[usize]) {
+        self.indices = corners.iter().map(|&corner| self.indices[corner]).collect();
+
+        for channel in self.normals.iter_mut() {
+            channel.indices = corners.iter().map(|&corner| channel.indices[corner]).collect();
+        }
+
+        for channel in self.tangents.iter_mut() {
+            channel.indices = corners.iter().map(|&corner| channel.indices[corner]).collect();
+        }
+
+        self.attributes = self.attributes.iter().map(|channel| channel.reorder_corners(corners)).collect();
+    }
+
+    // Orders positions, UVs and every channel's values by first use, leaving unused values last.
+    pub(super) fn reorder_values(&mut self) {
+        let (vertex_order, vertex_remap) = first_use(self.indices.iter().map(|&(i, _)| i), self.vertices.len());
+        let (uv_order, uv_remap) = first_use(self.indices.iter().map(|&(_, j)| j), self.uvs.len());
+
+        self.vertices = vertex_order.iter().map(|&i| self.vertices[i]).collect();
+        self.uvs = uv_order.iter().map(|&j| self.uvs[j]).collect();
+        self.indices = self.indices.iter().map(|&(i, j)| (vertex_remap[i], uv_remap[j])).collect();
+
+        for channel in self.normals.iter_mut() {
+            reorder_channel(channel);
+        }
+
+        for channel in self.tangents.iter_mut() {
+            reorder_channel(channel);
+        }
+
+        self.attributes = self.attributes.iter().map(|channel| {
+            channel.reorder_values(&vertex_order, &uv_order)
+        }).collect();
+    }
+
+    // Rebuilds the geometry as a list of the given triangles' corners, dropping the values no
+    // corner uses any more.
+    pub(super) fn with_triangles(&self, triangles: &[[usize; 3]]) -> Geometry {
+        let corners: Vec<_> = triangles.iter().flat_map(|triangle| triangle.iter().cloned()).collect();
+        let mut geometry = self.with_corners(Topology::TriangleList, &corners);
+
+        geometry.remove_unused_values();
+
+        geometry
+    }
+
+    // A copy with `topology` whose corner `k` is this geometry's corner `corners[k]`.
+    pub(super) fn with_corners(&self, topology: Topology, corners: &[usize]) -> Geometry {
+        let mut geometry = self.clone();
+
+        geometry.topology = topology;
+        geometry.reorder_corners(corners);
+
+        geometry
+    }
+
+    pub(super) fn set_vertices(&mut self, vertices: Vec<(f32, f32, f32)>) {
+        self.bounds = Bounds::from_points(&vertices);
+        self.vertices = vertices;
+    }
+
+    pub(super) fn remove_unused_values(&mut self) {
+        self.reorder_values();
+
+        // First use ordering leaves the used values in front.
+        let vertices = used(self.indices.iter().map(|&(i, _)| i));
+        let uvs = used(self.indices.iter().map(|&(_, j)| j));
+
+        self.vertices.truncate(vertices);
+        self.uvs.truncate(uvs);
+
+        for channel in self.normals.iter_mut() {
+            let len = used(channel.indices.iter().cloned());
+            channel.values.truncate(len);
+        }
+
+        for channel in self.tangents.iter_mut() {
+            let len = used(channel.indices.iter().cloned());
+            channel.values.truncate(len);
+        }
+
+        self.attributes = self.attributes.iter().map(|channel| channel.truncate(vertices, uvs)).collect();
+        self.bounds = Bounds::from_points(&self.vertices);
+    }
+
+    pub fn normals(&self) -> Option<&[(f32, f32, f32)]> {
+        self.normals.as_ref().map(|channel| &channel.values[..])
+    }
+
+    pub(super) fn set_normals(&mut self, normals: Vec<(f32, f32, f32)>, indices: Vec<usize>) {
+        self.normals = Some(Channel { values: normals, indices });
+    }
+
+    pub fn normal_indices(&self) -> Option<&[usize]> {
+        self.normals.as_ref().map(|channel| &channel.indices[..])
+    }
+
+    pub fn tangents(&self) -> Option<&[(f32, f32, f32, f32)]> {
+        self.tangents.as_ref().map(|channel| &channel.values[..])
+    }
+
+    pub fn tangent_indices(&self) -> Option<&[usize]> {
+        self.tangents.as_ref().map(|channel| &channel.indices[..])
+    }
+
+    pub(super) fn set_tangents(&mut self, tangents: Vec<(f32, f32, f32, f32)>, indices: Vec<usize>) {
+        self.tangents = Some(Channel { values: tangents, indices });
+    }
+
+    pub fn attributes(&self) -> &[AttributeChannel] {
+        &self.attributes[..]
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeChannel> {
+        self.attributes.iter().find(|channel| channel.attribute().name() == name)
+    }
+
     pub fn compress(&self) -> GeometryCompressed {
         let encoded: Vec<u8> = bincode::serialize(&self, bincode::Infinite).unwrap();
         let mut data = vec![];
@@ -79,31 +409,50 @@ impl Geometry {
         let mut compressor = BrotliEncoder::new(&encoded[..], 6);
         compressor.read_to_end(&mut data).unwrap();
 
-        GeometryCompressed { data }
+        GeometryCompressed { data, bounds: self.bounds }
     }
 
     pub fn expand(&self) -> GeometryExpanded {
+        let corners = self.list_corners();
+
         let mut vertices = vec![];
         let mut uvs = vec![];
 
-        for &(i, j) in &self.indices {
+        for &corner in &corners {
+            let (i, j) = self.indices[corner];
+
             vertices.push(self.vertices[i]);
             uvs.push(self.uvs[j]);
         }
 
         GeometryExpanded {
+            topology: self.topology.to_list(),
+            bounds: Bounds::from_points(&vertices),
             vertices,
-            uvs
+            uvs,
+            normals: self.normals.as_ref().map(|channel| expand_channel(channel, &corners)),
+            tangents: self.tangents.as_ref().map(|channel| expand_channel(channel, &corners)),
+            attributes: self.attributes.iter().map(|channel| {
+                channel.attribute().gather(corners.iter().map(|&corner| {
+                    channel.index(corner, self.indices[corner])
+                }))
+            }).collect()
         }
     }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GeometryCompressed {
-    data: Vec<u8>
+    data: Vec<u8>,
+    bounds: Option<Bounds>
 }
 
 impl GeometryCompressed {
+    // Kept outside the compressed data so culling never has to decompress.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
     pub fn decompress(&self) -> Geometry {
         let mut encoded = vec![];
 
@@ -116,8 +465,13 @@ impl GeometryCompressed {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct GeometryExpanded {
+    topology: Topology,
     vertices: Vec<(f32, f32, f32)>,
-    uvs: Vec<(f32, f32)>
+    uvs: Vec<(f32, f32)>,
+    normals: Option<Vec<(f32, f32, f32)>>,
+    tangents: Option<Vec<(f32, f32, f32, f32)>>,
+    attributes: Vec<Attribute>,
+    bounds: Option<Bounds>
 }
 
 impl GeometryExpanded {
@@ -125,26 +479,79 @@ impl GeometryExpanded {
         vertices: Vec<(f32, f32, f32)>,
         uvs: Vec<(f32, f32)>
     ) -> Option<GeometryExpanded> {
-        if vertices.len() != uvs.len() {
+        GeometryExpanded::with_topology(Topology::TriangleList, vertices, uvs)
+    }
+
+    pub fn with_topology(
+        topology: Topology,
+        vertices: Vec<(f32, f32, f32)>,
+        uvs: Vec<(f32, f32)>
+    ) -> Option<GeometryExpanded> {
+        if vertices.len() != uvs.len() || !topology.is_valid_len(vertices.len()) {
             return None;
         }
 
-        if vertices.iter().any(|&(x, y, z)| !x.is_finite() || !y.is_finite() || !z.is_finite()) {
+        if !vertices.iter().all(is_finite3) || !uvs.iter().all(is_finite2) {
             return None;
         }
 
-        if uvs.iter().any(|&(u, v)| !u.is_finite() || !v.is_finite()) {
+        Some(
+            GeometryExpanded {
+                topology,
+                bounds: Bounds::from_points(&vertices),
+                vertices,
+                uvs,
+                normals: None,
+                tangents: None,
+                attributes: vec![]
+            }
+        )
+    }
+
+    pub fn with_normals(self, normals: Vec<(f32, f32, f32)>) -> Option<GeometryExpanded> {
+        if normals.len() != self.vertices.len() || !normals.iter().all(is_finite3) {
             return None;
         }
 
         Some(
             GeometryExpanded {
-                vertices,
-                uvs
+                normals: Some(normals),
+                ..self
+            }
+        )
+    }
+
+    pub fn with_tangents(self, tangents: Vec<(f32, f32, f32, f32)>) -> Option<GeometryExpanded> {
+        if tangents.len() != self.vertices.len() || !tangents.iter().all(is_tangent) {
+            return None;
+        }
+
+        Some(
+            GeometryExpanded {
+                tangents: Some(tangents),
+                ..self
             }
         )
     }
 
+    pub fn with_attribute(mut self, attribute: Attribute) -> Option<GeometryExpanded> {
+        if attribute.len() != self.vertices.len() || self.attribute(attribute.name()).is_some() {
+            return None;
+        }
+
+        self.attributes.push(attribute);
+
+        Some(self)
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
     pub fn vertices(&self) -> &[(f32, f32, f32)] {
         &self.vertices[..]
     }
@@ -153,29 +560,69 @@ impl GeometryExpanded {
         &self.uvs[..]
     }
 
+    pub fn normals(&self) -> Option<&[(f32, f32, f32)]> {
+        self.normals.as_ref().map(|normals| &normals[..])
+    }
+
+    pub fn tangents(&self) -> Option<&[(f32, f32, f32, f32)]> {
+        self.tangents.as_ref().map(|tangents| &tangents[..])
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes[..]
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|attribute| attribute.name() == name)
+    }
+
     pub fn condense(&self) -> Geometry {
-        let mut vertices: Vec<_> = self.vertices.clone();
-        let mut uvs: Vec<_> = self.uvs.clone();
+        self.weld(&WeldOptions::new()).0
+    }
+}
 
-        vertices.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-        vertices.dedup_by(|a, b| a.eq(&b));
+pub(super) fn is_finite2(&(u, v): &(f32, f32)) -> bool {
+    u.is_finite() && v.is_finite()
+}
 
-        uvs.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-        uvs.dedup_by(|a, b| a.eq(&b));
+pub(super) fn is_finite3(&(x, y, z): &(f32, f32, f32)) -> bool {
+    x.is_finite() && y.is_finite() && z.is_finite()
+}
 
-        let indices = self.vertices.iter().zip(self.uvs.iter()).map(|(vertex, uv)| {
-            let i = vertices.binary_search_by(|probe| probe.partial_cmp(vertex).unwrap()).unwrap();
-            let j = uvs.binary_search_by(|probe| probe.partial_cmp(uv).unwrap()).unwrap();
+fn is_tangent(&(x, y, z, w): &(f32, f32, f32, f32)) -> bool {
+    is_finite3(&(x, y, z)) && (w == 1.0 || w == -1.0)
+}
 
-            (i, j)
-        }).collect();
+// Returns the values in order of first appearance followed by the unused ones, and where each
+// value moved to.
+pub(super) fn first_use<I: Iterator<Item = usize>>(indices: I, len: usize) -> (Vec<usize>, Vec<usize>) {
+    let mut order = vec![];
+    let mut remap = vec![None; len];
 
-        Geometry {
-            vertices,
-            uvs,
-            indices
+    for i in indices.chain(0..len) {
+        if remap[i].is_none() {
+            remap[i] = Some(order.len());
+            order.push(i);
         }
     }
+
+    (order, remap.into_iter().map(|i| i.unwrap()).collect())
+}
+
+// How many values the indices use, assuming they are in first use order.
+fn used<I: Iterator<Item = usize>>(indices: I) -> usize {
+    indices.max().map_or(0, |i| i + 1)
+}
+
+fn reorder_channel<T: Copy>(channel: &mut Channel<T>) {
+    let (order, remap) = first_use(channel.indices.iter().cloned(), channel.values.len());
+
+    channel.values = order.iter().map(|&i| channel.values[i]).collect();
+    channel.indices = channel.indices.iter().map(|&i| remap[i]).collect();
+}
+
+fn expand_channel<T: Copy>(channel: &Channel<T>, corners: &[usize]) -> Vec<T> {
+    corners.iter().map(|&corner| channel.values[channel.indices[corner]]).collect()
 }
 
 #[cfg(test)]
@@ -183,6 +630,7 @@ mod tests {
     use std::f32;
 
     use super::*;
+    use super::super::attribute::AttributeValues;
 
     #[test]
     fn new_geometry_nan() {
@@ -203,9 +651,14 @@ mod tests {
     #[test]
     fn geometry_getters() {
         let geometry = Geometry {
+            topology: Topology::TriangleList,
             vertices: vec![(1.0, 2.0, 3.0); 10],
             uvs: vec![(0.0, 1.0); 6],
-            indices: vec![(1, 2); 16]
+            indices: vec![(1, 2); 18],
+            normals: None,
+            tangents: None,
+            attributes: vec![],
+            bounds: None
         };
 
         assert_eq!(geometry.vertices(), &geometry.vertices[..]);
@@ -232,8 +685,13 @@ mod tests {
     #[test]
     fn geometry_expanded_getters() {
         let geometry = GeometryExpanded {
+            topology: Topology::TriangleList,
             vertices: vec![(1.0, 2.0, 3.0); 10],
-            uvs: vec![(0.0, 1.0); 10]
+            uvs: vec![(0.0, 1.0); 10],
+            normals: None,
+            tangents: None,
+            attributes: vec![],
+            bounds: None
         };
 
         assert_eq!(geometry.vertices(), &geometry.vertices[..]);
@@ -243,9 +701,14 @@ mod tests {
     #[test]
     fn compress_decompress() {
         let geometry = Geometry {
+            topology: Topology::TriangleList,
             vertices: vec![(1.0, 2.0, 3.0); 10],
             uvs: vec![(0.0, 1.0); 6],
-            indices: vec![(1, 2); 16]
+            indices: vec![(1, 2); 18],
+            normals: None,
+            tangents: None,
+            attributes: vec![],
+            bounds: None
         };
 
         let compressed = geometry.compress();
@@ -259,8 +722,13 @@ mod tests {
     #[test]
     fn condense_expand() {
         let expanded = GeometryExpanded {
+            topology: Topology::TriangleList,
             vertices: vec![(1.0, 2.0, 3.0); 3],
-            uvs: vec![(0.0, 1.0); 3]
+            uvs: vec![(0.0, 1.0); 3],
+            normals: None,
+            tangents: None,
+            attributes: vec![],
+            bounds: None
         };
 
         let geometry = expanded.condense();
@@ -274,13 +742,205 @@ mod tests {
         assert_eq!(geometry.indices, vec![(0, 0), (0, 0), (0, 0)]);
     }
 
+    #[test]
+    fn condense_nan() {
+        // Only reachable through deserialization, which skips the constructors' checks.
+        let expanded = GeometryExpanded {
+            topology: Topology::TriangleList,
+            vertices: vec![(f32::NAN, 0.0, 0.0), (f32::NAN, 0.0, 0.0), (1.0, 0.0, 0.0)],
+            uvs: vec![(0.0, 0.0), (-0.0, 0.0), (0.0, 0.0)],
+            normals: None,
+            tangents: None,
+            attributes: vec![],
+            bounds: None
+        };
+
+        let geometry = expanded.condense();
+
+        assert_eq!(geometry.vertices.len(), 3);
+        assert_eq!(geometry.uvs.len(), 1);
+    }
+
+    #[test]
+    fn topology_index_count() {
+        let geometry = |topology, len| {
+            Geometry::with_topology(topology, vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); len])
+        };
+
+        assert!(geometry(Topology::TriangleList, 16).is_none());
+        assert!(geometry(Topology::LineList, 16).is_some());
+        assert!(geometry(Topology::LineList, 3).is_none());
+        assert!(geometry(Topology::TriangleStrip, 2).is_none());
+        assert!(geometry(Topology::TriangleStrip, 5).is_some());
+        assert!(geometry(Topology::LineStrip, 1).is_none());
+        assert!(geometry(Topology::PointList, 1).is_some());
+
+        assert!(GeometryExpanded::with_topology(Topology::LineList, vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)]).is_none());
+    }
+
+    #[test]
+    fn expand_strips() {
+        let geometry = Geometry::with_topology(
+            Topology::TriangleStrip,
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0), (3, 0), (3, 0)]
+        ).unwrap();
+
+        let expanded = geometry.expand();
+        let v = geometry.vertices();
+
+        assert_eq!(expanded.topology(), Topology::TriangleList);
+        assert_eq!(expanded.vertices(), &[v[0], v[1], v[2], v[2], v[1], v[3]]);
+
+        let decompressed = geometry.compress().decompress();
+
+        assert_eq!(decompressed.topology(), Topology::TriangleStrip);
+        assert_eq!(decompressed.indices(), geometry.indices());
+
+        let lines = Geometry::with_topology(
+            Topology::LineStrip,
+            geometry.vertices().to_vec(),
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0)]
+        ).unwrap().expand();
+
+        assert_eq!(lines.topology(), Topology::LineList);
+        assert_eq!(lines.vertices(), &[v[0], v[1], v[1], v[2]]);
+        assert_eq!(lines.condense().topology(), Topology::LineList);
+    }
+
+    #[test]
+    fn with_normals_tangents_invalid() {
+        let geometry = || Geometry::new(vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); 3]).unwrap();
+
+        assert!(geometry().with_normals(vec![(f32::NAN, 0.0, 1.0)], vec![0; 3]).is_none());
+        assert!(geometry().with_normals(vec![(0.0, 0.0, 1.0)], vec![0; 2]).is_none());
+        assert!(geometry().with_normals(vec![(0.0, 0.0, 1.0)], vec![0, 0, 1]).is_none());
+        assert!(geometry().with_tangents(vec![(1.0, 0.0, 0.0, 0.5)], vec![0; 3]).is_none());
+        assert!(geometry().with_tangents(vec![(1.0, 0.0, 0.0, -1.0)], vec![0; 3]).is_some());
+
+        let expanded = || GeometryExpanded::new(vec![(0.0, 0.0, 0.0); 3], vec![(0.0, 0.0); 3]).unwrap();
+
+        assert!(expanded().with_normals(vec![]).is_none());
+        assert!(expanded().with_tangents(vec![(1.0, 0.0, 0.0, 2.0)]).is_none());
+    }
+
+    #[test]
+    fn normals_tangents_preserved() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            vec![(0, 0), (1, 1), (2, 2), (2, 2), (1, 1), (0, 0)]
+        ).unwrap().with_normals(
+            vec![(0.0, 0.0, 1.0), (0.0, 0.0, -1.0)],
+            vec![0, 0, 0, 1, 1, 1]
+        ).unwrap().with_tangents(
+            vec![(1.0, 0.0, 0.0, 1.0)],
+            vec![0; 6]
+        ).unwrap();
+
+        let decompressed = geometry.compress().decompress();
+
+        assert_eq!(decompressed.normals(), geometry.normals());
+        assert_eq!(decompressed.normal_indices(), geometry.normal_indices());
+        assert_eq!(decompressed.tangents(), geometry.tangents());
+
+        let expanded = geometry.expand();
+
+        assert_eq!(
+            expanded.normals().unwrap(),
+            &[(0.0, 0.0, 1.0), (0.0, 0.0, 1.0), (0.0, 0.0, 1.0),
+              (0.0, 0.0, -1.0), (0.0, 0.0, -1.0), (0.0, 0.0, -1.0)]
+        );
+        assert_eq!(expanded.tangents().unwrap(), &[(1.0, 0.0, 0.0, 1.0); 6]);
+
+        let condensed = expanded.condense();
+
+        assert_eq!(condensed.normals().unwrap(), &[(0.0, 0.0, 1.0), (0.0, 0.0, -1.0)]);
+        assert_eq!(condensed.normal_indices().unwrap(), &[0, 0, 0, 1, 1, 1]);
+        assert_eq!(condensed.tangents().unwrap(), &[(1.0, 0.0, 0.0, 1.0)]);
+        assert!(Geometry::new(vec![], vec![], vec![]).unwrap().expand().normals().is_none());
+    }
+
+    #[test]
+    fn with_attribute_invalid() {
+        let geometry = || Geometry::new(vec![(0.0, 0.0, 0.0); 2], vec![(0.0, 0.0)], vec![(0, 0); 3]).unwrap();
+        let weights = |len| Attribute::new("weight", 1, AttributeValues::F32(vec![1.0; len])).unwrap();
+
+        assert!(geometry().with_attribute(weights(1), AttributeIndices::Vertices).is_none());
+        assert!(geometry().with_attribute(weights(2), AttributeIndices::Uvs).is_none());
+        assert!(geometry().with_attribute(weights(1), AttributeIndices::Own(vec![0, 0])).is_none());
+        assert!(geometry().with_attribute(weights(1), AttributeIndices::Own(vec![0, 0, 1])).is_none());
+        assert!(
+            geometry()
+                .with_attribute(weights(2), AttributeIndices::Vertices).unwrap()
+                .with_attribute(weights(1), AttributeIndices::Uvs)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn attributes_preserved() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0)]
+        ).unwrap().with_attribute(
+            Attribute::new("joint", 1, AttributeValues::U8(vec![3, 4, 5])).unwrap(),
+            AttributeIndices::Vertices
+        ).unwrap().with_attribute(
+            Attribute::new("color", 4, AttributeValues::U8(vec![255, 0, 0, 255])).unwrap(),
+            AttributeIndices::Own(vec![0, 0, 0])
+        ).unwrap();
+
+        let decompressed = geometry.compress().decompress();
+
+        assert_eq!(decompressed.attributes(), geometry.attributes());
+
+        let expanded = geometry.expand();
+
+        assert_eq!(expanded.attribute("joint").unwrap().values(), &AttributeValues::U8(vec![3, 4, 5]));
+        assert_eq!(expanded.attribute("color").unwrap().len(), 3);
+
+        let condensed = expanded.condense();
+        let color = condensed.attribute("color").unwrap();
+
+        assert_eq!(color.attribute().values(), &AttributeValues::U8(vec![255, 0, 0, 255]));
+        assert_eq!(color.indices(), &AttributeIndices::Own(vec![0, 0, 0]));
+        assert_eq!(condensed.attribute("joint").unwrap().indices(), &AttributeIndices::Own(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn bounds() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (2.0, 0.0, 0.0), (0.0, 2.0, 0.0), (9.0, 9.0, 9.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0)]
+        ).unwrap();
+
+        let bounds = geometry.bounds().unwrap();
+
+        assert_eq!(bounds.aabb().max(), (9.0, 9.0, 9.0));
+        assert_eq!(geometry.compress().bounds(), Some(bounds));
+        assert_eq!(GeometryData::GeometryCompressed(geometry.compress()).bounds(), Some(bounds));
+
+        // Expanding drops the unused vertex.
+        let expanded = GeometryData::GeometryExpanded(geometry.expand()).bounds().unwrap();
+
+        assert_eq!(expanded.aabb().max(), (2.0, 2.0, 0.0));
+        assert_eq!(expanded.sphere().center(), (1.0, 1.0, 0.0));
+
+        assert!(Geometry::new(vec![], vec![], vec![]).unwrap().bounds().is_none());
+    }
+
     #[test]
     fn tie_untie() {
         let geometry = GeometryData::Geometry(
             Geometry::new(
                 vec![(1.0, 2.0, 3.0); 10],
                 vec![(0.0, 1.0); 6],
-                vec![(1, 2); 16]
+                vec![(1, 2); 18]
             ).unwrap()
         );
 
@@ -0,0 +1,332 @@
+use std::collections::{HashMap, HashSet};
+
+use super::geometry::{Geometry, Topology};
+
+// Adjacency of a triangle mesh by position. Half-edge `h` runs along face `h / 3` from its corner
+// `h % 3` to the next one, so faces, half-edges and corners of the list form share numbering.
+#[derive(Clone, Debug)]
+pub struct HalfEdgeMesh {
+    geometry: Geometry,
+    twins: Vec<Option<usize>>,
+    outgoing: Vec<Vec<usize>>
+}
+
+impl HalfEdgeMesh {
+    // Degenerate triangles are left out. Edges shared by more than two faces, or by two with
+    // conflicting winding, get no twins and so count as boundaries.
+    pub fn new(geometry: &Geometry) -> Option<HalfEdgeMesh> {
+        match geometry.topology() {
+            Topology::TriangleList | Topology::TriangleStrip => {}
+            _ => return None
+        }
+
+        let corners: Vec<_> = geometry.triangles().into_iter().filter(|triangle| {
+            let position = |k: usize| geometry.indices()[triangle[k]].0;
+            position(0) != position(1) && position(1) != position(2) && position(2) != position(0)
+        }).flat_map(|triangle| triangle.to_vec()).collect();
+
+        let geometry = geometry.with_corners(Topology::TriangleList, &corners);
+        let mut mesh = HalfEdgeMesh {
+            twins: vec![None; corners.len()],
+            outgoing: vec![vec![]; geometry.vertices().len()],
+            geometry
+        };
+
+        let mut directed: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+
+        for h in 0..mesh.len_half_edges() {
+            let (a, b) = (mesh.origin(h), mesh.target(h));
+
+            mesh.outgoing[a].push(h);
+            directed.entry((a, b)).or_default().push(h);
+        }
+
+        for h in 0..mesh.len_half_edges() {
+            let (a, b) = (mesh.origin(h), mesh.target(h));
+
+            if let (Some(forward), Some(backward)) = (directed.get(&(a, b)), directed.get(&(b, a))) {
+                if forward.len() == 1 && backward.len() == 1 {
+                    mesh.twins[h] = Some(backward[0]);
+                }
+            }
+        }
+
+        Some(mesh)
+    }
+
+    pub fn len_vertices(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    pub fn len_faces(&self) -> usize {
+        self.twins.len() / 3
+    }
+
+    pub fn len_half_edges(&self) -> usize {
+        self.twins.len()
+    }
+
+    pub fn origin(&self, h: usize) -> usize {
+        self.geometry.indices()[h].0
+    }
+
+    pub fn target(&self, h: usize) -> usize {
+        self.origin(self.next(h))
+    }
+
+    pub fn next(&self, h: usize) -> usize {
+        h - h % 3 + (h + 1) % 3
+    }
+
+    pub fn previous(&self, h: usize) -> usize {
+        h - h % 3 + (h + 2) % 3
+    }
+
+    pub fn twin(&self, h: usize) -> Option<usize> {
+        self.twins[h]
+    }
+
+    pub fn face(&self, h: usize) -> usize {
+        h / 3
+    }
+
+    pub fn face_vertices(&self, f: usize) -> [usize; 3] {
+        [self.origin(3 * f), self.origin(3 * f + 1), self.origin(3 * f + 2)]
+    }
+
+    pub fn outgoing(&self, v: usize) -> &[usize] {
+        &self.outgoing[v][..]
+    }
+
+    pub fn is_boundary_vertex(&self, v: usize) -> bool {
+        self.outgoing[v].iter().any(|&h| self.twins[h].is_none() || self.twins[self.previous(h)].is_none())
+    }
+
+    // Neighbouring positions, counterclockwise around each fan of faces, starting from a boundary
+    // where the fan has one.
+    pub fn vertex_ring(&self, v: usize) -> Vec<usize> {
+        let mut ring = vec![];
+        let mut visited = HashSet::new();
+        let starts = self.outgoing[v].iter().filter(|&&h| self.twins[h].is_none()).chain(self.outgoing[v].iter());
+
+        for &start in starts {
+            let mut h = start;
+
+            while visited.insert(h) {
+                ring.push(self.target(h));
+
+                let incoming = self.previous(h);
+
+                match self.twins[incoming] {
+                    Some(twin) => h = twin,
+                    None => {
+                        ring.push(self.origin(incoming));
+                        break;
+                    }
+                }
+            }
+        }
+
+        ring
+    }
+
+    // Faces on the edge between positions `a` and `b`, in either direction.
+    pub fn edge_faces(&self, a: usize, b: usize) -> Vec<usize> {
+        if a >= self.len_vertices() || b >= self.len_vertices() {
+            return vec![];
+        }
+
+        let forward = self.outgoing[a].iter().filter(|&&h| self.target(h) == b);
+        let backward = self.outgoing[b].iter().filter(|&&h| self.target(h) == a);
+
+        forward.chain(backward).map(|&h| self.face(h)).collect()
+    }
+
+    // Every chain of boundary half-edges as the positions it passes, in the faces' winding.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut loops = vec![];
+        let mut visited = vec![false; self.len_half_edges()];
+
+        for start in 0..self.len_half_edges() {
+            if self.twins[start].is_some() || visited[start] {
+                continue;
+            }
+
+            let mut positions = vec![];
+            let mut h = start;
+
+            while !visited[h] {
+                visited[h] = true;
+                positions.push(self.origin(h));
+
+                // Turns around the target until the next boundary half-edge.
+                h = self.next(h);
+
+                while let Some(twin) = self.twins[h] {
+                    h = self.next(twin);
+                }
+            }
+
+            loops.push(positions);
+        }
+
+        loops
+    }
+
+    // Faces grouped by the edges they share, each group in ascending order.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        let mut components = vec![];
+        let mut visited = vec![false; self.len_faces()];
+
+        for seed in 0..self.len_faces() {
+            if visited[seed] {
+                continue;
+            }
+
+            let mut component = vec![];
+            let mut stack = vec![seed];
+            visited[seed] = true;
+
+            while let Some(f) = stack.pop() {
+                component.push(f);
+
+                for h in 3 * f..3 * f + 3 {
+                    for neighbour in self.edge_faces(self.origin(h), self.target(h)) {
+                        if !visited[neighbour] {
+                            visited[neighbour] = true;
+                            stack.push(neighbour);
+                        }
+                    }
+                }
+            }
+
+            component.sort();
+            components.push(component);
+        }
+
+        components
+    }
+
+    // The faces as a triangle list, with every channel of the source geometry.
+    pub fn to_geometry(&self) -> Geometry {
+        self.geometry.clone()
+    }
+
+    // Only the given faces, without the values they leave unused.
+    pub fn faces_to_geometry(&self, faces: &[usize]) -> Option<Geometry> {
+        if faces.iter().any(|&f| f >= self.len_faces()) {
+            return None;
+        }
+
+        let triangles: Vec<_> = faces.iter().map(|&f| [3 * f, 3 * f + 1, 3 * f + 2]).collect();
+
+        Some(self.geometry.with_triangles(&triangles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::transform::Transform;
+
+    // An `n` by `n` grid of quads in the xy plane, split into triangles facing +z.
+    fn grid(n: usize) -> Geometry {
+        let vertices = (0..(n + 1) * (n + 1)).map(|i| ((i % (n + 1)) as f32, (i / (n + 1)) as f32, 0.0)).collect();
+        let indices = (0..n * n).flat_map(|q| {
+            let v = q / n * (n + 1) + q % n;
+            vec![v, v + 1, v + n + 2, v, v + n + 2, v + n + 1]
+        }).map(|v| (v, 0)).collect();
+
+        Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap()
+    }
+
+    #[test]
+    fn closed() {
+        let sphere = Geometry::icosphere(1.0, 1).unwrap();
+        let mesh = HalfEdgeMesh::new(&sphere).unwrap();
+
+        assert_eq!(mesh.len_faces(), 80);
+        assert!(mesh.boundary_loops().is_empty());
+        assert_eq!(mesh.connected_components(), vec![(0..80).collect::<Vec<_>>()]);
+
+        for h in 0..mesh.len_half_edges() {
+            let twin = mesh.twin(h).unwrap();
+
+            assert_eq!(mesh.twin(twin), Some(h));
+            assert_eq!((mesh.origin(twin), mesh.target(twin)), (mesh.target(h), mesh.origin(h)));
+            assert_eq!(mesh.edge_faces(mesh.origin(h), mesh.target(h)).len(), 2);
+        }
+
+        for v in 0..mesh.len_vertices() {
+            let ring = mesh.vertex_ring(v);
+
+            assert!(!mesh.is_boundary_vertex(v));
+            assert!(ring.len() == 5 || ring.len() == 6);
+            assert!(ring.iter().all(|&w| mesh.edge_faces(v, w).len() == 2));
+        }
+    }
+
+    #[test]
+    fn rings_and_boundaries() {
+        let mesh = HalfEdgeMesh::new(&grid(2)).unwrap();
+
+        // Counterclockwise around the center, and from the boundary around an edge vertex.
+        let ring = mesh.vertex_ring(4);
+        let start = ring.iter().position(|&v| v == 5).unwrap();
+        let rotated: Vec<_> = ring[start..].iter().chain(ring[..start].iter()).cloned().collect();
+
+        assert_eq!(rotated, vec![5, 8, 7, 3, 0, 1]);
+        assert_eq!(mesh.vertex_ring(1), vec![2, 5, 4, 0]);
+        assert_eq!(mesh.vertex_ring(0), vec![1, 4, 3]);
+        assert!(mesh.is_boundary_vertex(1));
+
+        let loops = mesh.boundary_loops();
+
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 8);
+
+        let start = loops[0].iter().position(|&v| v == 0).unwrap();
+        assert_eq!(loops[0][(start + 1) % 8], 1);
+
+        assert_eq!(mesh.edge_faces(0, 1).len(), 1);
+        assert!(mesh.edge_faces(0, 8).is_empty());
+    }
+
+    #[test]
+    fn components() {
+        let a = Geometry::icosphere(1.0, 0).unwrap();
+        let b = a.transform(&Transform::translation((3.0, 0.0, 0.0)).unwrap()).unwrap();
+        let merged = Geometry::merge(&[&a, &b]).unwrap();
+        let mesh = HalfEdgeMesh::new(&merged).unwrap();
+        let components = mesh.connected_components();
+
+        assert_eq!(components, vec![(0..20).collect::<Vec<_>>(), (20..40).collect::<Vec<_>>()]);
+
+        let part = mesh.faces_to_geometry(&components[1]).unwrap();
+
+        assert_eq!(part.vertices().len(), 12);
+        assert!(part.validate().is_closed());
+        assert!(mesh.faces_to_geometry(&[40]).is_none());
+    }
+
+    #[test]
+    fn conversion() {
+        let strip = Geometry::with_topology(
+            Topology::TriangleStrip,
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0), (3, 0), (3, 0)]
+        ).unwrap();
+        let mesh = HalfEdgeMesh::new(&strip).unwrap();
+        let geometry = mesh.to_geometry();
+
+        assert_eq!(mesh.len_faces(), 2);
+        assert_eq!(geometry.topology(), Topology::TriangleList);
+        assert_eq!(geometry.indices(), &[(0, 0), (1, 0), (2, 0), (2, 0), (1, 0), (3, 0)][..]);
+        assert_eq!(mesh.twin(1), Some(3));
+
+        let lines = Geometry::with_topology(Topology::LineList, vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); 2]).unwrap();
+
+        assert!(HalfEdgeMesh::new(&lines).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use super::attribute::Attribute;
+use super::geometry::{Geometry, Topology};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexBuffer {
+    U16(Vec<u16>),
+    U32(Vec<u32>)
+}
+
+impl IndexBuffer {
+    // 0xffff is left free so it can serve as the strip restart index.
+    fn new(indices: Vec<usize>, vertices: usize) -> IndexBuffer {
+        if vertices <= u16::MAX as usize {
+            IndexBuffer::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            IndexBuffer::U32(indices.into_iter().map(|i| i as u32).collect())
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            IndexBuffer::U16(ref indices) => indices.len(),
+            IndexBuffer::U32(ref indices) => indices.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> Option<usize> {
+        match *self {
+            IndexBuffer::U16(ref indices) => indices.get(i).map(|&index| index as usize),
+            IndexBuffer::U32(ref indices) => indices.get(i).map(|&index| index as usize)
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<usize> {
+        (0..self.len()).map(|i| self.get(i).unwrap()).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IndexedMesh {
+    topology: Topology,
+    vertices: Vec<(f32, f32, f32)>,
+    uvs: Vec<(f32, f32)>,
+    normals: Option<Vec<(f32, f32, f32)>>,
+    tangents: Option<Vec<(f32, f32, f32, f32)>>,
+    attributes: Vec<Attribute>,
+    indices: IndexBuffer
+}
+
+impl IndexedMesh {
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn len_vertices(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn vertices(&self) -> &[(f32, f32, f32)] {
+        &self.vertices[..]
+    }
+
+    pub fn uvs(&self) -> &[(f32, f32)] {
+        &self.uvs[..]
+    }
+
+    pub fn normals(&self) -> Option<&[(f32, f32, f32)]> {
+        self.normals.as_ref().map(|normals| &normals[..])
+    }
+
+    pub fn tangents(&self) -> Option<&[(f32, f32, f32, f32)]> {
+        self.tangents.as_ref().map(|tangents| &tangents[..])
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes[..]
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|attribute| attribute.name() == name)
+    }
+
+    pub fn indices(&self) -> &IndexBuffer {
+        &self.indices
+    }
+}
+
+impl Geometry {
+    // Corners become the same vertex when they share every channel's index, so sharing already
+    // present in the split indices carries over while values that merely compare equal do not.
+    // Returns every corner's vertex and the first corner of each vertex.
+    pub(super) fn corner_vertices(&self) -> (Vec<usize>, Vec<usize>) {
+        let key = |corner: usize| {
+            let (i, j) = self.indices()[corner];
+            let mut key = vec![i, j];
+
+            key.extend(self.normal_indices().map(|indices| indices[corner]));
+            key.extend(self.tangent_indices().map(|indices| indices[corner]));
+            key.extend(self.attributes().iter().map(|channel| channel.index(corner, (i, j))));
+
+            key
+        };
+
+        let mut firsts = vec![];
+        let mut unique = HashMap::new();
+
+        let vertices = (0..self.indices().len()).map(|corner| {
+            *unique.entry(key(corner)).or_insert_with(|| {
+                firsts.push(corner);
+                firsts.len() - 1
+            })
+        }).collect();
+
+        (vertices, firsts)
+    }
+
+    pub fn to_indexed_mesh(&self) -> IndexedMesh {
+        let (indices, firsts) = self.corner_vertices();
+
+        let gather = |values: &[usize]| firsts.iter().map(|&corner| values[corner]).collect::<Vec<_>>();
+
+        IndexedMesh {
+            topology: self.topology(),
+            vertices: firsts.iter().map(|&corner| self.vertices()[self.indices()[corner].0]).collect(),
+            uvs: firsts.iter().map(|&corner| self.uvs()[self.indices()[corner].1]).collect(),
+            normals: self.normals().map(|normals| {
+                gather(self.normal_indices().unwrap()).into_iter().map(|i| normals[i]).collect()
+            }),
+            tangents: self.tangents().map(|tangents| {
+                gather(self.tangent_indices().unwrap()).into_iter().map(|i| tangents[i]).collect()
+            }),
+            attributes: self.attributes().iter().map(|channel| {
+                channel.attribute().gather(firsts.iter().map(|&corner| {
+                    channel.index(corner, self.indices()[corner])
+                }))
+            }).collect(),
+            indices: IndexBuffer::new(indices, firsts.len())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::attribute::{AttributeIndices, AttributeValues};
+
+    #[test]
+    fn shared_corners() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.5, 0.5)],
+            vec![(0, 0), (1, 1), (2, 2), (0, 0), (2, 4), (3, 3)]
+        ).unwrap().with_normals(
+            vec![(0.0, 0.0, 1.0)],
+            vec![0; 6]
+        ).unwrap().with_attribute(
+            Attribute::new("weight", 1, AttributeValues::F32(vec![0.0, 1.0, 2.0, 3.0])).unwrap(),
+            AttributeIndices::Vertices
+        ).unwrap();
+
+        let mesh = geometry.to_indexed_mesh();
+
+        assert_eq!(mesh.topology(), Topology::TriangleList);
+        assert_eq!(mesh.len_vertices(), 5);
+        assert_eq!(mesh.indices(), &IndexBuffer::U16(vec![0, 1, 2, 0, 3, 4]));
+        assert_eq!(mesh.vertices()[3], (1.0, 1.0, 0.0));
+        assert_eq!(mesh.uvs()[3], (0.5, 0.5));
+        assert_eq!(mesh.normals().unwrap(), &[(0.0, 0.0, 1.0); 5]);
+        assert_eq!(
+            mesh.attribute("weight").unwrap().values(),
+            &AttributeValues::F32(vec![0.0, 1.0, 2.0, 2.0, 3.0])
+        );
+        assert!(mesh.tangents().is_none());
+    }
+
+    #[test]
+    fn split_by_normals() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0), (0, 0), (2, 0), (1, 0)]
+        ).unwrap().with_normals(
+            vec![(0.0, 0.0, 1.0), (0.0, 0.0, -1.0)],
+            vec![0, 0, 0, 1, 1, 1]
+        ).unwrap();
+
+        let mesh = geometry.to_indexed_mesh();
+
+        assert_eq!(mesh.len_vertices(), 6);
+        assert_eq!(mesh.indices().to_vec(), (0..6).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn index_width() {
+        let len = u16::MAX as usize + 3;
+        let vertices = (0..len).map(|i| (i as f32, 0.0, 0.0)).collect();
+        let indices: Vec<_> = (0..len).map(|i| (i, 0)).collect();
+
+        let geometry = Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap();
+        let mesh = geometry.to_indexed_mesh();
+
+        match *mesh.indices() {
+            IndexBuffer::U32(ref indices) => assert_eq!(indices[len - 1], len as u32 - 1),
+            _ => unreachable!()
+        }
+
+        let strip = Geometry::with_topology(
+            Topology::TriangleStrip,
+            vec![(0.0, 0.0, 0.0); 3],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0), (1, 0)]
+        ).unwrap().to_indexed_mesh();
+
+        assert_eq!(strip.topology(), Topology::TriangleStrip);
+        assert_eq!(strip.indices(), &IndexBuffer::U16(vec![0, 1, 2, 1]));
+        assert_eq!(strip.indices().get(4), None);
+    }
+}
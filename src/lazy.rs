@@ -0,0 +1,143 @@
+use std::cell::OnceCell;
+use std::fmt;
+use std::rc::Rc;
+
+use bincode;
+
+use super::block::Block;
+use super::yarn::{Leaf, Yarn};
+
+struct LazyInner<T> {
+    bytes: Option<Vec<u8>>,
+    value: OnceCell<Option<Rc<T>>>
+}
+
+pub struct Lazy<T> {
+    inner: Rc<LazyInner<T>>
+}
+
+impl<T: Leaf> Lazy<T> {
+    pub fn new(rc: Rc<T>) -> Lazy<T> {
+        let value = OnceCell::new();
+        let _ = value.set(Some(rc));
+
+        Lazy {
+            inner: Rc::new(LazyInner { bytes: None, value })
+        }
+    }
+
+    pub(super) fn encoded(bytes: Vec<u8>) -> Lazy<T> {
+        Lazy {
+            inner: Rc::new(LazyInner { bytes: Some(bytes), value: OnceCell::new() })
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded().is_some()
+    }
+
+    // `None` when the block could not be decoded.
+    pub fn get(&self) -> Option<&T> {
+        self.load().as_ref().map(|rc| &**rc)
+    }
+
+    pub fn into_rc(self) -> Option<Rc<T>> {
+        self.load().clone()
+    }
+
+    pub(super) fn loaded(&self) -> Option<Rc<T>> {
+        self.inner.value.get().and_then(|value| value.clone())
+    }
+
+    // The block this was untied from, kept so that tying it again does not need to decode it.
+    pub(super) fn bytes(&self) -> Option<&[u8]> {
+        self.inner.bytes.as_ref().map(|bytes| &bytes[..])
+    }
+
+    pub(super) fn ptr(&self) -> *const () {
+        &*self.inner as *const LazyInner<T> as *const ()
+    }
+
+    fn load(&self) -> &Option<Rc<T>> {
+        self.inner.value.get_or_init(|| {
+            let bytes = self.inner.bytes.as_ref()?;
+            let block = bincode::deserialize::<Block>(bytes).ok()?;
+
+            T::from_block(block, &mut Yarn::new()).map(Rc::new)
+        })
+    }
+}
+
+impl<T> Clone for Lazy<T> {
+    fn clone(&self) -> Lazy<T> {
+        Lazy {
+            inner: self.inner.clone()
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.inner.value.get(), self.inner.bytes.as_ref()) {
+            (Some(Some(rc)), _) => write!(f, "Lazy({:?})", rc),
+            (Some(None), _) => write!(f, "Lazy(<failed>)"),
+            (None, Some(bytes)) => write!(f, "Lazy(<{} bytes>)", bytes.len()),
+            (None, None) => write!(f, "Lazy(<empty>)")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::block::BlockInner;
+    use super::super::geometry::{Geometry, GeometryData};
+
+    fn geometry() -> GeometryData {
+        GeometryData::Geometry(
+            Geometry::new(
+                vec![(1.0, 2.0, 3.0); 10],
+                vec![(0.0, 1.0); 6],
+                vec![(1, 2); 18]
+            ).unwrap()
+        )
+    }
+
+    #[test]
+    fn load_on_access() {
+        let block = Block(BlockInner::GeometryData(geometry()));
+        let lazy: Lazy<GeometryData> =
+            Lazy::encoded(bincode::serialize(&block, bincode::Infinite).unwrap());
+        let clone = lazy.clone();
+
+        assert!(!lazy.is_loaded());
+
+        match *lazy.get().unwrap() {
+            GeometryData::Geometry(ref geometry) => {
+                assert_eq!(geometry.vertices(), &[(1.0, 2.0, 3.0); 10]);
+            }
+            _ => unreachable!()
+        }
+
+        assert!(clone.is_loaded());
+        assert_eq!(lazy.get().unwrap() as *const GeometryData, clone.get().unwrap() as *const GeometryData);
+    }
+
+    #[test]
+    fn load_failed() {
+        let lazy: Lazy<GeometryData> = Lazy::encoded(vec![0xff; 3]);
+
+        assert!(lazy.get().is_none());
+        assert!(!lazy.is_loaded());
+        assert!(lazy.into_rc().is_none());
+    }
+
+    #[test]
+    fn loaded() {
+        let rc = Rc::new(geometry());
+        let lazy = Lazy::new(rc.clone());
+
+        assert!(lazy.is_loaded());
+        assert!(Rc::ptr_eq(&lazy.into_rc().unwrap(), &rc));
+    }
+}
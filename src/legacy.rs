@@ -0,0 +1,233 @@
+use std::collections::VecDeque;
+use std::io::Read;
+
+use bincode;
+use brotli2::read::BrotliDecoder;
+
+use super::block::{Block, BlockInner};
+use super::geometry::{Geometry, GeometryData, GeometryExpanded, Topology};
+use super::object::BlockObject;
+
+// Yarns written before the format had a version: the magic followed by every block, without
+// metadata or roots, and geometry without topology or any channels but positions and UVs.
+#[derive(Deserialize)]
+enum LegacyBlock {
+    GeometryData(LegacyGeometryData),
+    Object(LegacyObject)
+}
+
+#[derive(Deserialize)]
+enum LegacyGeometryData {
+    Geometry(LegacyGeometry),
+    GeometryCompressed(LegacyGeometryCompressed),
+    GeometryExpanded(LegacyGeometryExpanded)
+}
+
+#[derive(Deserialize)]
+struct LegacyGeometry {
+    vertices: Vec<(f32, f32, f32)>,
+    uvs: Vec<(f32, f32)>,
+    indices: Vec<(usize, usize)>
+}
+
+#[derive(Deserialize)]
+struct LegacyGeometryCompressed {
+    data: Vec<u8>
+}
+
+#[derive(Deserialize)]
+struct LegacyGeometryExpanded {
+    vertices: Vec<(f32, f32, f32)>,
+    uvs: Vec<(f32, f32)>
+}
+
+#[derive(Deserialize)]
+struct LegacyObject {
+    geometry_index: usize
+}
+
+impl LegacyGeometry {
+    fn upgrade(self) -> Option<Geometry> {
+        Geometry::with_topology(topology(self.indices.len()), self.vertices, self.uvs, self.indices)
+    }
+}
+
+impl LegacyGeometryData {
+    fn upgrade(self) -> Option<GeometryData> {
+        match self {
+            LegacyGeometryData::Geometry(geometry) => Some(GeometryData::Geometry(geometry.upgrade()?)),
+            LegacyGeometryData::GeometryCompressed(compressed) => {
+                let mut encoded = vec![];
+
+                BrotliDecoder::new(&compressed.data[..]).read_to_end(&mut encoded).ok()?;
+
+                let geometry: LegacyGeometry = bincode::deserialize(&encoded[..]).ok()?;
+
+                Some(GeometryData::GeometryCompressed(geometry.upgrade()?.compress()))
+            }
+            LegacyGeometryData::GeometryExpanded(expanded) => {
+                let topology = topology(expanded.vertices.len());
+
+                Some(GeometryData::GeometryExpanded(
+                    GeometryExpanded::with_topology(topology, expanded.vertices, expanded.uvs)?
+                ))
+            }
+        }
+    }
+}
+
+// Nothing checked that old geometry held whole triangles, so any that does not is kept as points
+// rather than failing the whole yarn.
+fn topology(len: usize) -> Topology {
+    if len.is_multiple_of(3) {
+        Topology::TriangleList
+    } else {
+        Topology::PointList
+    }
+}
+
+// Objects were written before the geometry they share, and unties took whichever block came
+// first, so the roots are the blocks no object refers to, in order.
+pub(super) fn read(bytes: &[u8]) -> Option<(Vec<Block>, VecDeque<usize>)> {
+    let legacy: Vec<LegacyBlock> = bincode::deserialize(bytes).ok()?;
+    let mut referenced = vec![false; legacy.len()];
+
+    for block in &legacy {
+        if let LegacyBlock::Object(ref object) = *block {
+            match legacy.get(object.geometry_index) {
+                Some(&LegacyBlock::GeometryData(_)) => referenced[object.geometry_index] = true,
+                _ => return None
+            }
+        }
+    }
+
+    let roots = (0..legacy.len()).filter(|&index| !referenced[index]).collect();
+    let blocks = legacy.into_iter().map(|block| {
+        match block {
+            LegacyBlock::GeometryData(data) => Some(Block(BlockInner::GeometryData(data.upgrade()?))),
+            LegacyBlock::Object(LegacyObject { geometry_index }) => {
+                Some(Block(BlockInner::Object(BlockObject { geometry_index })))
+            }
+        }
+    }).collect::<Option<_>>()?;
+
+    Some((blocks, roots))
+}
+
+#[cfg(test)]
+mod tests {
+    use brotli2::read::BrotliEncoder;
+
+    use super::*;
+    use super::super::object::Object;
+    use super::super::yarn::{Tie, Yarn};
+
+    #[derive(Serialize)]
+    enum OldBlock {
+        GeometryData(OldGeometryData),
+        Object(usize)
+    }
+
+    #[derive(Serialize)]
+    enum OldGeometryData {
+        Geometry(Vec<(f32, f32, f32)>, Vec<(f32, f32)>, Vec<(usize, usize)>),
+        GeometryCompressed(Vec<u8>),
+        GeometryExpanded(Vec<(f32, f32, f32)>, Vec<(f32, f32)>)
+    }
+
+    fn encode(blocks: &[OldBlock]) -> Vec<u8> {
+        bincode::serialize(&blocks, bincode::Infinite).unwrap()
+    }
+
+    #[test]
+    fn upgrade() {
+        let geometry = || OldBlock::GeometryData(OldGeometryData::Geometry(
+            vec![(1.0, 2.0, 3.0)],
+            vec![(0.0, 1.0)],
+            vec![(0, 0); 3]
+        ));
+        let bytes = encode(&[OldBlock::Object(2), OldBlock::Object(2), geometry(), geometry()]);
+        let (blocks, roots) = read(&bytes).unwrap();
+
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(roots, vec![0, 1, 3]);
+
+        match blocks[2] {
+            Block(BlockInner::GeometryData(GeometryData::Geometry(ref geometry))) => {
+                assert_eq!(geometry.vertices(), &[(1.0, 2.0, 3.0)]);
+                assert_eq!(geometry.indices(), &[(0, 0); 3]);
+            }
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn partial_triangles() {
+        let parts = (vec![(1.0f32, 2.0f32, 3.0f32)], vec![(0.0f32, 1.0f32)], vec![(0usize, 0usize); 16]);
+        let encoded = bincode::serialize(&parts, bincode::Infinite).unwrap();
+        let mut data = vec![];
+
+        BrotliEncoder::new(&encoded[..], 6).read_to_end(&mut data).unwrap();
+
+        let bytes = encode(&[
+            OldBlock::GeometryData(OldGeometryData::Geometry(parts.0, parts.1, parts.2)),
+            OldBlock::GeometryData(OldGeometryData::GeometryExpanded(vec![(1.0, 2.0, 3.0); 16], vec![(0.0, 1.0); 16])),
+            OldBlock::GeometryData(OldGeometryData::GeometryCompressed(data))
+        ]);
+        let (blocks, roots) = read(&bytes).unwrap();
+
+        assert_eq!(roots, vec![0, 1, 2]);
+
+        match blocks[0] {
+            Block(BlockInner::GeometryData(GeometryData::Geometry(ref geometry))) => {
+                assert_eq!(geometry.topology(), Topology::PointList);
+                assert_eq!(geometry.indices().len(), 16);
+            }
+            _ => unreachable!()
+        }
+
+        match blocks[1] {
+            Block(BlockInner::GeometryData(GeometryData::GeometryExpanded(ref expanded))) => {
+                assert_eq!(expanded.topology(), Topology::PointList);
+                assert_eq!(expanded.vertices().len(), 16);
+            }
+            _ => unreachable!()
+        }
+
+        match blocks[2] {
+            Block(BlockInner::GeometryData(GeometryData::GeometryCompressed(ref compressed))) => {
+                assert_eq!(compressed.decompress().topology(), Topology::PointList);
+            }
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(read(&encode(&[OldBlock::Object(1)])).is_none());
+        assert!(read(&encode(&[OldBlock::Object(0)])).is_none());
+        assert!(read(&[0xff; 3]).is_none());
+    }
+
+    #[test]
+    fn untie() {
+        let mut bytes = b"yarn".to_vec();
+
+        bytes.extend(encode(&[
+            OldBlock::Object(1),
+            OldBlock::GeometryData(OldGeometryData::Geometry(vec![(4.0, 2.0, 3.0)], vec![(0.0, 1.0)], vec![(0, 0); 3]))
+        ]));
+
+        let mut yarn = Yarn::from_bytes(&bytes).unwrap();
+        let object = Object::untie(&mut yarn).unwrap();
+
+        match *object.geometry().unwrap() {
+            GeometryData::Geometry(ref geometry) => assert_eq!(geometry.vertices(), &[(4.0, 2.0, 3.0)]),
+            _ => unreachable!()
+        }
+
+        assert!(yarn.metadata().is_none());
+        assert!(Yarn::metadata_from_bytes(&bytes).is_none());
+        assert!(Yarn::from_bytes(b"yar").is_none());
+    }
+}
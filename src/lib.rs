@@ -5,8 +5,32 @@ extern crate brotli2;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod attribute;
 pub mod block;
+pub mod bounds;
+pub mod bundle;
+pub mod bvh;
 pub mod geometry;
+pub mod halfedge;
+pub mod indexed;
+pub mod lazy;
+pub mod lod;
 pub mod material;
+pub mod measure;
+mod legacy;
+mod math;
+pub mod meshlet;
+pub mod metadata;
+pub mod normal;
 pub mod object;
+pub mod optimize;
+pub mod polygon;
+pub mod primitive;
+pub mod simplify;
+pub mod subdivide;
+pub mod tangent;
+pub mod transform;
+pub mod validate;
+pub mod vertex;
+pub mod weld;
 pub mod yarn;
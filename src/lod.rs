@@ -0,0 +1,184 @@
+use super::geometry::Geometry;
+use super::simplify::SimplifyOptions;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GeometryLod {
+    geometry: Geometry,
+    error: f32
+}
+
+impl GeometryLod {
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+
+    // Object-space distance the level may deviate from the full detail geometry.
+    pub fn error(&self) -> f32 {
+        self.error
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GeometryLods {
+    levels: Vec<GeometryLod>
+}
+
+impl GeometryLods {
+    // Levels go from the most to the least detailed, so errors must not decrease.
+    pub fn new(levels: Vec<(Geometry, f32)>) -> Option<GeometryLods> {
+        if levels.is_empty() {
+            return None;
+        }
+
+        if levels.iter().any(|&(_, error)| !error.is_finite() || error < 0.0) {
+            return None;
+        }
+
+        if levels.windows(2).any(|pair| pair[1].1 < pair[0].1) {
+            return None;
+        }
+
+        Some(
+            GeometryLods {
+                levels: levels.into_iter().map(|(geometry, error)| GeometryLod { geometry, error }).collect()
+            }
+        )
+    }
+
+    pub fn levels(&self) -> &[GeometryLod] {
+        &self.levels[..]
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    // Errors are stored in object space so they hold for any camera; a level's screen-space
+    // threshold is its error times `pixels_per_unit`, the pixels one unit covers at the object's
+    // distance (`viewport_height / (2 * distance * tan(fov_y / 2))` for a perspective camera).
+    // Picks the coarsest level whose threshold stays within `max_pixel_error`, or the finest.
+    // Only deserialized levels can be empty.
+    pub fn select(&self, pixels_per_unit: f32, max_pixel_error: f32) -> Option<&GeometryLod> {
+        self.levels.iter().rev()
+            .find(|level| level.error * pixels_per_unit <= max_pixel_error)
+            .or_else(|| self.levels.first())
+    }
+}
+
+impl Geometry {
+    // Level 0 is this geometry; each ratio of its triangle count adds a level simplified from the
+    // previous one, whose error bounds the sum of both.
+    pub fn build_lods(&self, ratios: &[f32], options: &SimplifyOptions) -> Option<GeometryLods> {
+        let mut levels = vec![(self.clone(), 0.0f32)];
+        let triangles = self.indices().len() as f32;
+
+        for &ratio in ratios {
+            let (geometry, error) = {
+                let &(ref previous, previous_error) = levels.last().unwrap();
+                let ratio = ratio * triangles / (previous.indices().len() as f32).max(1.0);
+                let (geometry, error) = previous.simplify(&options.with_target_ratio(ratio.min(1.0))?)?;
+
+                (geometry, previous_error + error)
+            };
+
+            levels.push((geometry, error));
+        }
+
+        GeometryLods::new(levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use bincode;
+
+    use super::*;
+    use super::super::block::{Block, BlockInner};
+    use super::super::geometry::GeometryData;
+
+    fn bumpy() -> Geometry {
+        let n = 8;
+        let vertices = (0..(n + 1) * (n + 1)).map(|i| {
+            let (x, y) = (i % (n + 1), i / (n + 1));
+            (x as f32, y as f32, ((x * 7 + y * 3) % 5) as f32 * 0.1)
+        }).collect();
+        let indices = (0..n * n).flat_map(|q| {
+            let v = q / n * (n + 1) + q % n;
+            vec![v, v + 1, v + n + 2, v, v + n + 2, v + n + 1]
+        }).map(|v| (v, 0)).collect();
+
+        Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap()
+    }
+
+    #[test]
+    fn build_select() {
+        let lods = bumpy().build_lods(&[0.5, 0.25], &SimplifyOptions::new()).unwrap();
+        let levels = lods.levels();
+
+        assert_eq!(lods.len(), 3);
+        assert_eq!(levels[0].error(), 0.0);
+        assert!(levels[1].geometry().indices().len() <= 64 * 3);
+        assert!(levels[2].geometry().indices().len() < levels[1].geometry().indices().len());
+        assert!(levels[1].error() <= levels[2].error());
+
+        assert!(lods.select(1e6, 1.0).unwrap().error() <= 1e-6);
+        assert_eq!(lods.select(0.0, 1.0).unwrap().error(), levels[2].error());
+    }
+
+    #[test]
+    fn select_screen_space() {
+        let lods = bumpy().build_lods(&[0.5, 0.25, 0.125], &SimplifyOptions::new()).unwrap();
+        let levels = lods.levels();
+
+        // A 1080 pixel high viewport with a 60 degree vertical field of view.
+        let pixels_per_unit = |distance: f32| 1080.0 / (2.0 * distance * 30f32.to_radians().tan());
+        let index = |level: &GeometryLod| levels.iter().position(|other| ptr::eq(other, level));
+
+        let near = lods.select(pixels_per_unit(1.0), 1.0).unwrap();
+        let middle = lods.select(pixels_per_unit(50.0), 1.0).unwrap();
+        let far = lods.select(pixels_per_unit(5000.0), 1.0).unwrap();
+
+        assert!(index(near) <= index(middle) && index(middle) <= index(far));
+        assert_eq!(index(far), Some(levels.len() - 1));
+
+        for &(level, distance) in &[(near, 1.0), (middle, 50.0), (far, 5000.0)] {
+            assert!(level.error() == 0.0 || level.error() * pixels_per_unit(distance) <= 1.0);
+        }
+
+        // A looser threshold never picks a finer level.
+        assert!(index(lods.select(pixels_per_unit(50.0), 4.0).unwrap()) >= index(middle));
+        assert!(!lods.is_empty());
+    }
+
+    #[test]
+    fn new_invalid() {
+        assert!(GeometryLods::new(vec![]).is_none());
+        assert!(GeometryLods::new(vec![(bumpy(), 1.0), (bumpy(), 0.5)]).is_none());
+        assert!(GeometryLods::new(vec![(bumpy(), -1.0)]).is_none());
+    }
+
+    #[test]
+    fn serialize() {
+        let lods = bumpy().build_lods(&[0.5], &SimplifyOptions::new()).unwrap();
+        let block = Block(BlockInner::GeometryData(GeometryData::GeometryLods(lods)));
+        let bytes = bincode::serialize(&block, bincode::Infinite).unwrap();
+
+        match bincode::deserialize::<Block>(&bytes).unwrap() {
+            Block(BlockInner::GeometryData(GeometryData::GeometryLods(lods))) => assert_eq!(lods.len(), 2),
+            _ => unreachable!()
+        }
+
+        // Deserializing skips `new`, so no levels at all must not panic.
+        let empty: GeometryLods = bincode::deserialize(&[0; 8]).unwrap();
+
+        assert!(empty.is_empty());
+        assert!(empty.select(1.0, 1.0).is_none());
+        assert!(GeometryData::GeometryLods(empty).bounds().is_none());
+    }
+}
@@ -0,0 +1,56 @@
+pub(super) type Vec3 = (f32, f32, f32);
+
+pub(super) fn add((ax, ay, az): Vec3, (bx, by, bz): Vec3) -> Vec3 {
+    (ax + bx, ay + by, az + bz)
+}
+
+pub(super) fn sub((ax, ay, az): Vec3, (bx, by, bz): Vec3) -> Vec3 {
+    (ax - bx, ay - by, az - bz)
+}
+
+pub(super) fn scale((x, y, z): Vec3, s: f32) -> Vec3 {
+    (x * s, y * s, z * s)
+}
+
+pub(super) fn dot((ax, ay, az): Vec3, (bx, by, bz): Vec3) -> f32 {
+    ax * bx + ay * by + az * bz
+}
+
+pub(super) fn cross((ax, ay, az): Vec3, (bx, by, bz): Vec3) -> Vec3 {
+    (ay * bz - az * by, az * bx - ax * bz, ax * by - ay * bx)
+}
+
+pub(super) fn length(v: Vec3) -> f32 {
+    dot(v, v).sqrt()
+}
+
+// Zero vectors are returned unchanged.
+pub(super) fn normalize(v: Vec3) -> Vec3 {
+    let length = length(v);
+
+    if length > 0.0 {
+        scale(v, 1.0 / length)
+    } else {
+        v
+    }
+}
+
+pub(super) fn angle(a: Vec3, b: Vec3) -> f32 {
+    let denominator = length(a) * length(b);
+
+    if denominator > 0.0 {
+        (dot(a, b) / denominator).clamp(-1.0, 1.0).acos()
+    } else {
+        0.0
+    }
+}
+
+pub(super) type Vec2 = (f32, f32);
+
+pub(super) fn sub2((ax, ay): Vec2, (bx, by): Vec2) -> Vec2 {
+    (ax - bx, ay - by)
+}
+
+pub(super) fn cross2((ax, ay): Vec2, (bx, by): Vec2) -> f32 {
+    ax * by - ay * bx
+}
@@ -0,0 +1,257 @@
+use super::geometry::Geometry;
+use super::math::{self, Vec3};
+
+// Volume, centroid and covariance about the centroid.
+type MassProperties = (f64, (f64, f64, f64), [[f64; 3]; 3]);
+
+impl Geometry {
+    pub fn surface_area(&self) -> f32 {
+        self.triangle_positions().iter().map(|triangle| {
+            math::length(math::cross(math::sub(triangle[1], triangle[0]), math::sub(triangle[2], triangle[0]))) as f64
+        }).sum::<f64>() as f32 / 2.0
+    }
+
+    // Area-weighted mean of the triangles' centers, or `None` without any area.
+    pub fn surface_centroid(&self) -> Option<(f32, f32, f32)> {
+        let (area, sum) = self.triangle_positions().iter().fold((0.0f64, (0.0f64, 0.0f64, 0.0f64)), |(area, sum), triangle| {
+            let weight = math::length(math::cross(math::sub(triangle[1], triangle[0]), math::sub(triangle[2], triangle[0]))) as f64;
+            let center = wide(math::scale(math::add(triangle[0], math::add(triangle[1], triangle[2])), 1.0 / 3.0));
+
+            (area + weight, (sum.0 + center.0 * weight, sum.1 + center.1 * weight, sum.2 + center.2 * weight))
+        });
+
+        if area > 0.0 {
+            Some(((sum.0 / area) as f32, (sum.1 / area) as f32, (sum.2 / area) as f32))
+        } else {
+            None
+        }
+    }
+
+    // Only closed, consistently wound meshes enclose a volume. It is negative when the triangles
+    // face inwards.
+    pub fn volume(&self) -> Option<f32> {
+        self.mass_properties().map(|(volume, _, _)| volume as f32)
+    }
+
+    // Center of the enclosed volume.
+    pub fn centroid(&self) -> Option<(f32, f32, f32)> {
+        let (volume, centroid, _) = self.mass_properties()?;
+
+        if volume == 0.0 {
+            return None;
+        }
+
+        Some((centroid.0 as f32, centroid.1 as f32, centroid.2 as f32))
+    }
+
+    // Inertia tensor of the enclosed volume at uniform `density`, about its centroid.
+    pub fn inertia_tensor(&self, density: f32) -> Option<[[f32; 3]; 3]> {
+        let (volume, _, covariance) = self.mass_properties()?;
+
+        if volume == 0.0 || !density.is_finite() || density < 0.0 {
+            return None;
+        }
+
+        let density = density as f64;
+        let trace = covariance[0][0] + covariance[1][1] + covariance[2][2];
+        let mut tensor = [[0.0; 3]; 3];
+
+        for i in 0..3 {
+            for j in 0..3 {
+                let identity = if i == j { trace } else { 0.0 };
+                tensor[i][j] = ((identity - covariance[i][j]) * density) as f32;
+            }
+        }
+
+        Some(tensor)
+    }
+
+    pub fn uv_area(&self) -> f32 {
+        self.triangles().into_iter().map(|triangle| {
+            let uv = |k: usize| self.uvs()[self.indices()[triangle[k]].1];
+            math::cross2(math::sub2(uv(1), uv(0)), math::sub2(uv(2), uv(0))).abs() as f64
+        }).sum::<f64>() as f32 / 2.0
+    }
+
+    // Texels per object space unit for a texture of `width` by `height`, averaged over the
+    // surface, or `None` without any area.
+    pub fn texel_density(&self, width: u32, height: u32) -> Option<f32> {
+        let area = self.surface_area();
+
+        if area.is_nan() || area <= 0.0 {
+            return None;
+        }
+
+        Some((self.uv_area() as f64 * width as f64 * height as f64 / area as f64).sqrt() as f32)
+    }
+
+    fn triangle_positions(&self) -> Vec<[Vec3; 3]> {
+        self.triangles().into_iter().map(|triangle| {
+            let position = |k: usize| self.vertices()[self.indices()[triangle[k]].0];
+            [position(0), position(1), position(2)]
+        }).collect()
+    }
+
+    // Sums the signed tetrahedra between the origin and every triangle into the volume, centroid
+    // and covariance about the centroid, following Blow and Binstock's "How to find the inertia
+    // tensor (or other mass properties) of a 3D solid body represented by a triangle mesh".
+    fn mass_properties(&self) -> Option<MassProperties> {
+        let report = self.validate();
+
+        if self.triangles().is_empty() || !report.is_closed() ||
+            !report.non_manifold_edges().is_empty() || !report.inconsistent_edges().is_empty() {
+            return None;
+        }
+
+        let canonical = [[2.0, 1.0, 1.0], [1.0, 2.0, 1.0], [1.0, 1.0, 2.0]];
+        let mut volume = 0.0;
+        let mut first_moment = (0.0, 0.0, 0.0);
+        let mut covariance = [[0.0f64; 3]; 3];
+
+        for triangle in self.triangle_positions() {
+            let columns = [wide(triangle[0]), wide(triangle[1]), wide(triangle[2])];
+            let (a, b, c) = (columns[0], columns[1], columns[2]);
+            let determinant = a.0 * (b.1 * c.2 - b.2 * c.1) - a.1 * (b.0 * c.2 - b.2 * c.0) + a.2 * (b.0 * c.1 - b.1 * c.0);
+            let component = |v: (f64, f64, f64), i: usize| match i {
+                0 => v.0,
+                1 => v.1,
+                _ => v.2
+            };
+
+            volume += determinant / 6.0;
+            first_moment = (
+                first_moment.0 + determinant * (a.0 + b.0 + c.0) / 24.0,
+                first_moment.1 + determinant * (a.1 + b.1 + c.1) / 24.0,
+                first_moment.2 + determinant * (a.2 + b.2 + c.2) / 24.0
+            );
+
+            for (i, row) in covariance.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    let mut sum = 0.0;
+
+                    for k in 0..3 {
+                        for l in 0..3 {
+                            sum += component(columns[k], i) * canonical[k][l] * component(columns[l], j);
+                        }
+                    }
+
+                    *value += determinant * sum / 120.0;
+                }
+            }
+        }
+
+        let centroid = if volume != 0.0 {
+            (first_moment.0 / volume, first_moment.1 / volume, first_moment.2 / volume)
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+        let component = |i: usize| match i {
+            0 => centroid.0,
+            1 => centroid.1,
+            _ => centroid.2
+        };
+
+        // Moves the covariance from the origin to the centroid.
+        for (i, row) in covariance.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value -= volume * component(i) * component(j);
+            }
+        }
+
+        Some((volume, centroid, covariance))
+    }
+}
+
+fn wide((x, y, z): Vec3) -> (f64, f64, f64) {
+    (x as f64, y as f64, z as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A box from `min` with the given size, its faces wound outwards.
+    fn cuboid(min: Vec3, (sx, sy, sz): Vec3) -> Geometry {
+        let vertices = (0..8).map(|i| {
+            (
+                min.0 + if i & 1 != 0 { sx } else { 0.0 },
+                min.1 + if i & 2 != 0 { sy } else { 0.0 },
+                min.2 + if i & 4 != 0 { sz } else { 0.0 }
+            )
+        }).collect();
+        let faces = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        let indices = faces.iter().flat_map(|face| {
+            vec![face[0], face[1], face[2], face[0], face[2], face[3]]
+        }).map(|v| (v, 0)).collect();
+
+        Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap()
+    }
+
+    #[test]
+    fn closed_box() {
+        let geometry = cuboid((1.0, 2.0, 3.0), (1.0, 2.0, 3.0));
+
+        assert!((geometry.surface_area() - 22.0).abs() < 1e-5);
+        assert!((geometry.volume().unwrap() - 6.0).abs() < 1e-5);
+
+        let centroid = geometry.centroid().unwrap();
+        let surface = geometry.surface_centroid().unwrap();
+
+        assert!(math::length(math::sub(centroid, (1.5, 3.0, 4.5))) < 1e-5);
+        assert!(math::length(math::sub(surface, (1.5, 3.0, 4.5))) < 1e-5);
+    }
+
+    #[test]
+    fn inertia() {
+        let geometry = cuboid((5.0, -1.0, 2.0), (1.0, 2.0, 3.0));
+        let tensor = geometry.inertia_tensor(2.0).unwrap();
+
+        // A solid box has I = m / 12 * (b² + c²) about each axis and no products of inertia.
+        let mass = 12.0;
+        let expected = [mass / 12.0 * 13.0, mass / 12.0 * 10.0, mass / 12.0 * 5.0];
+
+        for i in 0..3 {
+            for j in 0..3 {
+                let value = if i == j { expected[i] } else { 0.0 };
+                assert!((tensor[i][j] - value).abs() < 1e-3, "{:?}", tensor);
+            }
+        }
+    }
+
+    #[test]
+    fn open_and_inverted() {
+        let geometry = cuboid((0.0, 0.0, 0.0), (1.0, 1.0, 1.0));
+        let open = Geometry::new(
+            geometry.vertices().to_vec(),
+            vec![(0.0, 0.0)],
+            geometry.indices()[..30].to_vec()
+        ).unwrap();
+
+        assert!(open.volume().is_none());
+        assert!(open.inertia_tensor(1.0).is_none());
+        assert!((open.surface_area() - 5.0).abs() < 1e-5);
+
+        let inverted = Geometry::new(
+            geometry.vertices().to_vec(),
+            vec![(0.0, 0.0)],
+            geometry.indices().chunks(3).flat_map(|triangle| vec![triangle[0], triangle[2], triangle[1]]).collect()
+        ).unwrap();
+
+        assert!((inverted.volume().unwrap() + 1.0).abs() < 1e-5);
+        assert!(math::length(math::sub(inverted.centroid().unwrap(), (0.5, 0.5, 0.5))) < 1e-5);
+    }
+
+    #[test]
+    fn uv_area_texel_density() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (2.0, 0.0, 0.0), (2.0, 2.0, 0.0), (0.0, 2.0, 0.0)],
+            vec![(0.0, 0.0), (0.5, 0.0), (0.5, 0.5), (0.0, 0.5)],
+            vec![(0, 0), (1, 1), (2, 2), (0, 0), (2, 2), (3, 3)]
+        ).unwrap();
+
+        assert_eq!(geometry.uv_area(), 0.25);
+        assert_eq!(geometry.surface_area(), 4.0);
+        assert_eq!(geometry.texel_density(1024, 1024), Some(256.0));
+        assert!(Geometry::new(vec![], vec![], vec![]).unwrap().texel_density(1, 1).is_none());
+    }
+}
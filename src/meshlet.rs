@@ -0,0 +1,433 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use super::block::{Block, BlockInner};
+use super::bounds::Bounds;
+use super::geometry::{Geometry, GeometryData, Topology};
+use super::lazy::Lazy;
+use super::math::{self, Vec3};
+use super::yarn::{Tie, Yarn};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshletOptions {
+    max_vertices: usize,
+    max_triangles: usize
+}
+
+impl MeshletOptions {
+    pub fn new() -> MeshletOptions {
+        MeshletOptions {
+            max_vertices: 64,
+            max_triangles: 124
+        }
+    }
+
+    // Local indices are stored in a byte, so a meshlet has at most 256 vertices.
+    pub fn with_max_vertices(self, max_vertices: usize) -> Option<MeshletOptions> {
+        if !(3..=256).contains(&max_vertices) {
+            return None;
+        }
+
+        Some(MeshletOptions { max_vertices, ..self })
+    }
+
+    pub fn with_max_triangles(self, max_triangles: usize) -> Option<MeshletOptions> {
+        if max_triangles == 0 {
+            return None;
+        }
+
+        Some(MeshletOptions { max_triangles, ..self })
+    }
+
+    pub fn max_vertices(&self) -> usize {
+        self.max_vertices
+    }
+
+    pub fn max_triangles(&self) -> usize {
+        self.max_triangles
+    }
+}
+
+impl Default for MeshletOptions {
+    fn default() -> MeshletOptions {
+        MeshletOptions::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NormalCone {
+    apex: (f32, f32, f32),
+    axis: (f32, f32, f32),
+    cutoff: f32
+}
+
+impl NormalCone {
+    pub fn apex(&self) -> (f32, f32, f32) {
+        self.apex
+    }
+
+    pub fn axis(&self) -> (f32, f32, f32) {
+        self.axis
+    }
+
+    // Sine of the widest angle between the axis and a triangle's normal, or 1 when the normals
+    // spread too far for the cone to ever cull.
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    // True when every triangle faces away from the camera.
+    pub fn is_backfacing(&self, camera: (f32, f32, f32)) -> bool {
+        if self.cutoff >= 1.0 {
+            return false;
+        }
+
+        math::dot(math::normalize(math::sub(self.apex, camera)), self.axis) >= self.cutoff
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Meshlet {
+    vertices: Vec<usize>,
+    triangles: Vec<[u8; 3]>,
+    bounds: Bounds,
+    cone: NormalCone
+}
+
+impl Meshlet {
+    // Vertices of the geometry's indexed mesh.
+    pub fn vertices(&self) -> &[usize] {
+        &self.vertices[..]
+    }
+
+    // Triangles as indices into `vertices`.
+    pub fn triangles(&self) -> &[[u8; 3]] {
+        &self.triangles[..]
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    pub fn cone(&self) -> &NormalCone {
+        &self.cone
+    }
+}
+
+#[derive(Debug)]
+pub struct Meshlets {
+    geometry: Lazy<GeometryData>,
+    meshlets: Vec<Meshlet>
+}
+
+impl Meshlets {
+    pub fn new(geometry: Rc<GeometryData>, options: &MeshletOptions) -> Option<Meshlets> {
+        let meshlets = match *geometry {
+            GeometryData::Geometry(ref geometry) => build(geometry, options)?,
+            _ => return None
+        };
+
+        Some(
+            Meshlets {
+                geometry: Lazy::new(geometry),
+                meshlets
+            }
+        )
+    }
+
+    pub fn is_geometry_loaded(&self) -> bool {
+        self.geometry.is_loaded()
+    }
+
+    // `None` when the geometry block could not be decoded.
+    pub fn geometry(&self) -> Option<&GeometryData> {
+        self.geometry.get()
+    }
+
+    pub fn meshlets(&self) -> &[Meshlet] {
+        &self.meshlets[..]
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshlets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshlets.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct BlockMeshlets {
+    pub(super) geometry_index: usize,
+    pub(super) meshlets: Vec<Meshlet>
+}
+
+impl Tie for Meshlets {
+    fn into_block(self, yarn: &mut Yarn) -> Block {
+        let geometry_index = yarn.tie_lazy(self.geometry);
+
+        Block(BlockInner::Meshlets(
+            BlockMeshlets {
+                geometry_index,
+                meshlets: self.meshlets
+            }
+        ))
+    }
+
+    fn from_block(block: Block, yarn: &mut Yarn) -> Option<Self> {
+        match block {
+            Block(BlockInner::Meshlets(BlockMeshlets { geometry_index, meshlets })) => {
+                Some(
+                    Meshlets {
+                        geometry: yarn.untie_lazy(geometry_index)?,
+                        meshlets
+                    }
+                )
+            }
+            _ => unreachable!()
+        }
+    }
+}
+
+// Grows each meshlet greedily from its first triangle, always adding the neighbouring triangle
+// that brings the fewest new vertices, and starts a new one from the first unused triangle
+// once nothing more fits.
+fn build(geometry: &Geometry, options: &MeshletOptions) -> Option<Vec<Meshlet>> {
+    match geometry.topology() {
+        Topology::TriangleList | Topology::TriangleStrip => {}
+        _ => return None
+    }
+
+    let (corner_vertices, firsts) = geometry.corner_vertices();
+    let triangles: Vec<[usize; 3]> = geometry.triangles().into_iter().map(|triangle| {
+        [corner_vertices[triangle[0]], corner_vertices[triangle[1]], corner_vertices[triangle[2]]]
+    }).collect();
+    let positions: Vec<Vec3> = firsts.iter().map(|&corner| geometry.vertices()[geometry.indices()[corner].0]).collect();
+
+    let mut adjacency = vec![vec![]; firsts.len()];
+
+    for (t, triangle) in triangles.iter().enumerate() {
+        for &vertex in triangle {
+            adjacency[vertex].push(t);
+        }
+    }
+
+    let mut used = vec![false; triangles.len()];
+    let mut meshlets = vec![];
+    let mut next = 0;
+
+    loop {
+        while next < triangles.len() && used[next] {
+            next += 1;
+        }
+
+        if next == triangles.len() {
+            break;
+        }
+
+        let mut local = HashMap::new();
+        let mut vertices = vec![];
+        let mut members = vec![];
+        // Neighbouring triangles queued by how many new vertices they would bring. A triangle is
+        // queued again whenever that drops, and the entries left behind are skipped.
+        let mut new_vertices = HashMap::new();
+        let mut queues = [VecDeque::new(), VecDeque::new(), VecDeque::new()];
+        let mut seed = Some(next);
+
+        while let Some(t) = seed {
+            used[t] = true;
+            members.push(t);
+
+            for &vertex in &triangles[t] {
+                if let Entry::Vacant(entry) = local.entry(vertex) {
+                    entry.insert(vertices.len());
+                    vertices.push(vertex);
+
+                    for &neighbor in adjacency[vertex].iter().filter(|&&neighbor| !used[neighbor]) {
+                        let count = triangles[neighbor].iter().filter(|vertex| !local.contains_key(vertex)).count();
+
+                        new_vertices.insert(neighbor, count);
+                        queues[count].push_back(neighbor);
+                    }
+                }
+            }
+
+            if members.len() == options.max_triangles {
+                break;
+            }
+
+            // Adding vertices never makes a triangle fit again, so one that does not fit is dropped.
+            seed = queues.iter_mut().enumerate().filter_map(|(count, queue)| {
+                while let Some(t) = queue.pop_front() {
+                    if !used[t] && new_vertices[&t] == count && vertices.len() + count <= options.max_vertices {
+                        return Some(t);
+                    }
+                }
+
+                None
+            }).next();
+        }
+
+        let local_triangles = members.iter().map(|&t| {
+            let index = |i: usize| local[&triangles[t][i]] as u8;
+            [index(0), index(1), index(2)]
+        }).collect();
+
+        let points: Vec<_> = vertices.iter().map(|&vertex| positions[vertex]).collect();
+        let corners: Vec<_> = members.iter().map(|&t| {
+            [positions[triangles[t][0]], positions[triangles[t][1]], positions[triangles[t][2]]]
+        }).collect();
+        let bounds = Bounds::from_points(&points).unwrap();
+        let cone = normal_cone(&corners, bounds.sphere().center());
+
+        meshlets.push(Meshlet { vertices, triangles: local_triangles, bounds, cone });
+    }
+
+    Some(meshlets)
+}
+
+// The axis averages the unit normals of the non-degenerate triangles, and the apex is moved back
+// along it until every triangle's plane passes in front of it.
+fn normal_cone(triangles: &[[Vec3; 3]], center: Vec3) -> NormalCone {
+    let normals: Vec<_> = triangles.iter().map(|triangle| {
+        math::normalize(math::cross(math::sub(triangle[1], triangle[0]), math::sub(triangle[2], triangle[0])))
+    }).collect();
+
+    let axis = math::normalize(normals.iter().fold((0.0, 0.0, 0.0), |sum, &normal| math::add(sum, normal)));
+    let unculled = NormalCone { apex: center, axis, cutoff: 1.0 };
+
+    if math::length(axis) == 0.0 {
+        return unculled;
+    }
+
+    let spread = normals.iter().filter(|&&normal| math::length(normal) > 0.0)
+        .map(|&normal| math::dot(normal, axis))
+        .fold(1.0, f32::min);
+
+    // Past roughly 84 degrees the apex would be pushed unreasonably far back.
+    if spread <= 0.1 {
+        return unculled;
+    }
+
+    let back = triangles.iter().zip(&normals).filter(|&(_, &normal)| math::length(normal) > 0.0)
+        .map(|(triangle, &normal)| math::dot(math::sub(center, triangle[0]), normal) / math::dot(axis, normal))
+        .fold(0.0, f32::max);
+
+    NormalCone {
+        apex: math::sub(center, math::scale(axis, back)),
+        axis,
+        cutoff: (1.0 - spread * spread).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::yarn::{Tie, Yarn};
+
+    fn grid(n: usize) -> Geometry {
+        let vertices = (0..(n + 1) * (n + 1)).map(|i| ((i % (n + 1)) as f32, (i / (n + 1)) as f32, 0.0)).collect();
+        let indices = (0..n * n).flat_map(|q| {
+            let v = q / n * (n + 1) + q % n;
+            vec![v, v + 1, v + n + 2, v, v + n + 2, v + n + 1]
+        }).map(|v| (v, 0)).collect();
+
+        Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap()
+    }
+
+    #[test]
+    fn limits_and_coverage() {
+        let geometry = grid(16);
+        let options = MeshletOptions::new().with_max_vertices(32).unwrap().with_max_triangles(40).unwrap();
+        let meshlets = Meshlets::new(Rc::new(GeometryData::Geometry(geometry.clone())), &options).unwrap();
+
+        let mesh = geometry.to_indexed_mesh();
+        let mut expected: Vec<_> = mesh.indices().to_vec().chunks(3).map(|chunk| chunk.to_vec()).collect();
+        let mut found = vec![];
+
+        for meshlet in meshlets.meshlets() {
+            assert!(meshlet.vertices().len() <= 32);
+            assert!(meshlet.triangles().len() <= 40);
+
+            for triangle in meshlet.triangles() {
+                found.push(triangle.iter().map(|&i| meshlet.vertices()[i as usize]).collect::<Vec<_>>());
+            }
+
+            for &vertex in meshlet.vertices() {
+                assert!(meshlet.bounds().aabb().contains(mesh.vertices()[vertex]));
+            }
+        }
+
+        expected.sort();
+        found.sort();
+
+        assert_eq!(found, expected);
+        // Growing by neighbours keeps meshlets compact, close to the triangle limit.
+        assert!(meshlets.len() <= 16 * 16 * 2 / 40 + 3);
+    }
+
+    #[test]
+    fn cone_culling() {
+        let meshlets = Meshlets::new(Rc::new(GeometryData::Geometry(grid(4))), &MeshletOptions::new()).unwrap();
+        let cone = meshlets.meshlets()[0].cone();
+
+        assert_eq!(meshlets.len(), 1);
+        assert!(math::length(math::sub(cone.axis(), (0.0, 0.0, 1.0))) < 1e-6);
+        assert!(cone.cutoff().abs() < 1e-3);
+        assert!(cone.is_backfacing((2.0, 2.0, -5.0)));
+        assert!(!cone.is_backfacing((2.0, 2.0, 5.0)));
+
+        // A steep tent's sides face nearly opposite ways, so it is never culled as a whole.
+        let tent = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 20.0), (2.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 20.0), (2.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (4, 0), (0, 0), (4, 0), (3, 0), (1, 0), (2, 0), (5, 0), (1, 0), (5, 0), (4, 0)]
+        ).unwrap();
+        let meshlets = Meshlets::new(Rc::new(GeometryData::Geometry(tent)), &MeshletOptions::new()).unwrap();
+        let cone = meshlets.meshlets()[0].cone();
+
+        assert!(cone.cutoff() >= 1.0);
+        assert!(!cone.is_backfacing((1.0, 0.5, -5.0)));
+    }
+
+    #[test]
+    fn options_invalid() {
+        assert!(MeshletOptions::new().with_max_vertices(2).is_none());
+        assert!(MeshletOptions::new().with_max_vertices(257).is_none());
+        assert!(MeshletOptions::new().with_max_triangles(0).is_none());
+        assert_eq!(MeshletOptions::default(), MeshletOptions::new());
+
+        let lines = Geometry::with_topology(
+            Topology::LineList,
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0)]
+        ).unwrap();
+
+        assert!(Meshlets::new(Rc::new(GeometryData::Geometry(lines)), &MeshletOptions::new()).is_none());
+    }
+
+    #[test]
+    fn tie_untie() {
+        let meshlets = Meshlets::new(Rc::new(GeometryData::Geometry(grid(8))), &MeshletOptions::new()).unwrap();
+        let expected = meshlets.meshlets().to_vec();
+
+        let mut yarn = Yarn::new();
+
+        meshlets.tie(&mut yarn);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+        let meshlets = Meshlets::untie(&mut yarn).unwrap();
+
+        assert!(!meshlets.is_geometry_loaded());
+        assert!(!meshlets.is_empty());
+        assert_eq!(meshlets.meshlets(), &expected[..]);
+
+        match *meshlets.geometry().unwrap() {
+            GeometryData::Geometry(ref geometry) => assert_eq!(geometry.indices().len(), 8 * 8 * 6),
+            _ => unreachable!()
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Iter;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum MetadataValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Path(PathBuf),
+    Timestamp(u64),
+    Tags(Vec<String>)
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Metadata {
+    entries: BTreeMap<String, MetadataValue>
+}
+
+impl Metadata {
+    pub fn new() -> Metadata {
+        Metadata {
+            entries: BTreeMap::new()
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&MetadataValue> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, key: &str, value: MetadataValue) -> Option<MetadataValue> {
+        self.entries.insert(key.to_owned(), value)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<MetadataValue> {
+        self.entries.remove(key)
+    }
+
+    pub fn iter<'a>(&'a self) -> Iter<'a, String, MetadataValue> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut metadata = Metadata::new();
+
+        assert!(metadata.is_empty());
+
+        metadata.insert("tool", MetadataValue::String("exporter 1.2".to_owned()));
+        metadata.insert("created", MetadataValue::Timestamp(1_500_000_000));
+
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata.get("created"), Some(&MetadataValue::Timestamp(1_500_000_000)));
+
+        let old = metadata.insert("created", MetadataValue::Timestamp(1_600_000_000));
+
+        assert_eq!(old, Some(MetadataValue::Timestamp(1_500_000_000)));
+        assert_eq!(metadata.remove("tool"), Some(MetadataValue::String("exporter 1.2".to_owned())));
+        assert_eq!(metadata.iter().map(|(key, _)| &key[..]).collect::<Vec<_>>(), vec!["created"]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use super::geometry::Geometry;
+use super::math::{self, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalMode {
+    Flat,
+    AreaWeighted,
+    AngleWeighted,
+    Crease(f32)
+}
+
+impl Geometry {
+    pub fn compute_normals(&mut self, mode: NormalMode) {
+        let triangles = self.triangles();
+        let owners = self.corner_owners(&triangles);
+
+        let corner_position = |corner: usize| self.vertices()[self.indices()[corner].0];
+
+        // Unnormalized, so its length is twice the triangle's area.
+        let face_normals: Vec<Vec3> = triangles.iter().map(|triangle| {
+            let a = corner_position(triangle[0]);
+            let b = corner_position(triangle[1]);
+            let c = corner_position(triangle[2]);
+
+            math::cross(math::sub(b, a), math::sub(c, a))
+        }).collect();
+
+        let corner_angle = |t: usize, i: usize| {
+            let triangle = triangles[t];
+            let a = corner_position(triangle[i]);
+            let b = corner_position(triangle[(i + 1) % 3]);
+            let c = corner_position(triangle[(i + 2) % 3]);
+
+            math::angle(math::sub(b, a), math::sub(c, a))
+        };
+
+        let (mut normals, indices): (Vec<Vec3>, Vec<Option<usize>>) = match mode {
+            NormalMode::Flat => {
+                let normals = face_normals.iter().map(|&normal| math::normalize(normal)).collect();
+                let indices = owners.iter().map(|owner| owner.map(|(t, _)| t)).collect();
+
+                (normals, indices)
+            }
+            NormalMode::AreaWeighted | NormalMode::AngleWeighted => {
+                let mut normals = vec![(0.0, 0.0, 0.0); self.vertices().len()];
+
+                for (t, triangle) in triangles.iter().enumerate() {
+                    for (i, &corner) in triangle.iter().enumerate() {
+                        let normal = match mode {
+                            NormalMode::AreaWeighted => face_normals[t],
+                            _ => math::scale(math::normalize(face_normals[t]), corner_angle(t, i))
+                        };
+                        let v = self.indices()[corner].0;
+
+                        normals[v] = math::add(normals[v], normal);
+                    }
+                }
+
+                let normals = normals.into_iter().map(math::normalize).collect();
+                let indices = owners.iter().zip(self.indices().iter()).map(|(owner, &(v, _))| {
+                    owner.map(|_| v)
+                }).collect();
+
+                (normals, indices)
+            }
+            NormalMode::Crease(crease) => {
+                let threshold = crease.cos();
+                let units: Vec<_> = face_normals.iter().map(|&normal| math::normalize(normal)).collect();
+
+                let mut incident = vec![vec![]; self.vertices().len()];
+
+                for (t, triangle) in triangles.iter().enumerate() {
+                    for (i, &corner) in triangle.iter().enumerate() {
+                        incident[self.indices()[corner].0].push((t, i));
+                    }
+                }
+
+                let mut normals = vec![];
+                let mut unique = HashMap::new();
+
+                let indices = owners.iter().enumerate().map(|(corner, owner)| {
+                    owner.map(|(t, _)| {
+                        let unit = units[t];
+
+                        let normal = incident[self.indices()[corner].0].iter()
+                            .filter(|&&(other, _)| math::dot(unit, units[other]) >= threshold)
+                            .fold((0.0, 0.0, 0.0), |sum, &(other, i)| {
+                                math::add(sum, math::scale(units[other], corner_angle(other, i)))
+                            });
+                        let normal = math::normalize(normal);
+
+                        let key = (normal.0.to_bits(), normal.1.to_bits(), normal.2.to_bits());
+
+                        *unique.entry(key).or_insert_with(|| {
+                            normals.push(normal);
+                            normals.len() - 1
+                        })
+                    })
+                }).collect();
+
+                (normals, indices)
+            }
+        };
+
+        // Corners that are not part of any triangle get a zero normal.
+        let zero = normals.len();
+
+        if indices.iter().any(|index| index.is_none()) {
+            normals.push((0.0, 0.0, 0.0));
+        }
+
+        let indices = indices.into_iter().map(|index| index.unwrap_or(zero)).collect();
+
+        self.set_normals(normals, indices);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use super::super::geometry::Topology;
+
+    fn cube() -> Geometry {
+        let vertices = (0..8).map(|i| {
+            ((i & 1) as f32 * 2.0 - 1.0, (i >> 1 & 1) as f32 * 2.0 - 1.0, (i >> 2 & 1) as f32 * 2.0 - 1.0)
+        }).collect();
+        let quads = [[0, 4, 6, 2], [1, 3, 7, 5], [0, 1, 5, 4], [2, 6, 7, 3], [0, 2, 3, 1], [4, 5, 7, 6]];
+
+        let indices = quads.iter().flat_map(|quad| {
+            vec![quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]
+        }).map(|i| (i, 0)).collect();
+
+        Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap()
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!(math::length(math::sub(a, b)) < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn flat() {
+        let mut geometry = cube();
+
+        geometry.compute_normals(NormalMode::Flat);
+
+        let normals = geometry.normals().unwrap();
+        let indices = geometry.normal_indices().unwrap();
+
+        assert_eq!(normals.len(), 12);
+        assert_close(normals[indices[0]], (-1.0, 0.0, 0.0));
+        assert_close(normals[indices[35]], (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn smooth() {
+        let corner = 1.0 / 3.0f32.sqrt();
+
+        for &mode in &[NormalMode::AreaWeighted, NormalMode::AngleWeighted] {
+            let mut geometry = cube();
+
+            geometry.compute_normals(mode);
+
+            let normals = geometry.normals().unwrap();
+
+            assert_eq!(normals.len(), 8);
+            assert_eq!(geometry.normal_indices().unwrap()[..3], [0, 4, 6]);
+            assert_close(normals[7], (corner, corner, corner));
+            assert_close(normals[0], (-corner, -corner, -corner));
+        }
+    }
+
+    #[test]
+    fn crease() {
+        let mut geometry = cube();
+
+        geometry.compute_normals(NormalMode::Crease(PI / 3.0));
+
+        let normals = geometry.normals().unwrap();
+        let indices = geometry.normal_indices().unwrap();
+
+        assert_eq!(normals.len(), 6);
+        assert_close(normals[indices[6]], (1.0, 0.0, 0.0));
+
+        let mut geometry = cube();
+
+        geometry.compute_normals(NormalMode::Crease(PI));
+
+        assert_eq!(geometry.normals().unwrap().len(), 8);
+    }
+
+    #[test]
+    fn strip() {
+        let mut geometry = Geometry::with_topology(
+            Topology::TriangleStrip,
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0), (3, 0)]
+        ).unwrap();
+
+        geometry.compute_normals(NormalMode::Flat);
+
+        let normals = geometry.normals().unwrap();
+
+        for &i in geometry.normal_indices().unwrap() {
+            assert_close(normals[i], (0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn degenerate_strip() {
+        let mut geometry = Geometry::with_topology(
+            Topology::TriangleStrip,
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0), (2, 0)]
+        ).unwrap();
+
+        geometry.compute_normals(NormalMode::Flat);
+
+        assert_eq!(geometry.normals().unwrap(), &[(0.0, 0.0, 1.0), (0.0, 0.0, 0.0)]);
+        assert_eq!(geometry.normal_indices().unwrap(), &[0, 0, 0, 1]);
+    }
+}
@@ -2,33 +2,39 @@ use std::rc::Rc;
 
 use super::block::{Block, BlockInner};
 use super::geometry::GeometryData;
+use super::lazy::Lazy;
 use super::yarn::{Tie, Yarn};
 
 #[derive(Debug)]
 pub struct Object {
-    geometry: Rc<GeometryData>
+    geometry: Lazy<GeometryData>
 }
 
 impl Object {
     pub fn new(geometry: Rc<GeometryData>) -> Object {
-        Object { geometry }
+        Object {
+            geometry: Lazy::new(geometry)
+        }
+    }
+
+    pub fn is_geometry_loaded(&self) -> bool {
+        self.geometry.is_loaded()
     }
 
-    pub fn geometry(&self) -> &GeometryData {
-        &*self.geometry
+    // `None` when the geometry block could not be decoded.
+    pub fn geometry(&self) -> Option<&GeometryData> {
+        self.geometry.get()
     }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(super) struct BlockObject {
-    geometry_index: usize
+    pub(super) geometry_index: usize
 }
 
 impl Tie for Object {
     fn into_block(self, yarn: &mut Yarn) -> Block {
-        yarn.allocate_block();
-
-        let geometry_index = yarn.tie_rc(self.geometry);
+        let geometry_index = yarn.tie_lazy(self.geometry);
 
         Block(BlockInner::Object(
             BlockObject { geometry_index }
@@ -38,10 +44,9 @@ impl Tie for Object {
     fn from_block(block: Block, yarn: &mut Yarn) -> Option<Self> {
         match block {
             Block(BlockInner::Object(BlockObject { geometry_index })) => {
-                let rc = yarn.untie_rc(geometry_index)?;
                 Some(
                     Object {
-                        geometry: rc.downcast().ok()?
+                        geometry: yarn.untie_lazy(geometry_index)?
                     }
                 )
             }
@@ -65,7 +70,7 @@ mod tests {
                 Geometry::new(
                     vec![(1.0, 2.0, 3.0); 10],
                     vec![(0.0, 1.0); 6],
-                    vec![(1, 2); 16]
+                    vec![(1, 2); 18]
                 ).unwrap()
             )
         );
@@ -80,22 +85,105 @@ mod tests {
         let object1 = Object::untie(&mut yarn).unwrap();
         let object2 = Object::untie(&mut yarn).unwrap();
 
-        match *object1.geometry() {
+        match *object1.geometry().unwrap() {
             GeometryData::Geometry(ref geometry) => {
                 assert_eq!(geometry.vertices(), &[(1.0, 2.0, 3.0); 10]);
             }
             _ => unreachable!()
         }
-        match *object2.geometry() {
+        match *object2.geometry().unwrap() {
+            GeometryData::Geometry(ref geometry) => {
+                assert_eq!(geometry.vertices(), &[(1.0, 2.0, 3.0); 10]);
+            }
+            _ => unreachable!()
+        }
+
+        assert_eq!(
+            object1.geometry().unwrap() as *const GeometryData,
+            object2.geometry().unwrap() as *const GeometryData
+        );
+    }
+
+    fn round_trip(object1: Object, object2: Object) -> (Object, Object) {
+        let mut yarn = Yarn::new();
+
+        object1.tie(&mut yarn);
+        object2.tie(&mut yarn);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+
+        (Object::untie(&mut yarn).unwrap(), Object::untie(&mut yarn).unwrap())
+    }
+
+    #[test]
+    fn untie_lazy() {
+        let geometry = Rc::new(
+            GeometryData::Geometry(
+                Geometry::new(
+                    vec![(1.0, 2.0, 3.0); 10],
+                    vec![(0.0, 1.0); 6],
+                    vec![(1, 2); 18]
+                ).unwrap()
+            )
+        );
+
+        let (object1, object2) = round_trip(Object::new(geometry.clone()), Object::new(geometry));
+
+        assert!(!object1.is_geometry_loaded());
+        assert!(!object2.is_geometry_loaded());
+
+        match *object1.geometry().unwrap() {
             GeometryData::Geometry(ref geometry) => {
                 assert_eq!(geometry.vertices(), &[(1.0, 2.0, 3.0); 10]);
             }
             _ => unreachable!()
         }
 
+        assert!(object2.is_geometry_loaded());
+
+        let (object1, object2) = round_trip(object1, object2);
+
+        assert_eq!(
+            object1.geometry().unwrap() as *const GeometryData,
+            object2.geometry().unwrap() as *const GeometryData
+        );
+    }
+
+    #[test]
+    fn tie_unread() {
+        let geometry = Rc::new(
+            GeometryData::Geometry(
+                Geometry::new(
+                    vec![(1.0, 2.0, 3.0); 10],
+                    vec![(0.0, 1.0); 6],
+                    vec![(1, 2); 18]
+                ).unwrap()
+            )
+        );
+
+        let (object1, object2) = round_trip(Object::new(geometry.clone()), Object::new(geometry));
+        let (object1, object2) = round_trip(object1, object2);
+
+        assert!(!object1.is_geometry_loaded());
+        assert!(!object2.is_geometry_loaded());
         assert_eq!(
-            object1.geometry() as *const GeometryData,
-            object2.geometry() as *const GeometryData
+            object1.geometry().unwrap() as *const GeometryData,
+            object2.geometry().unwrap() as *const GeometryData
         );
     }
+
+    #[test]
+    fn corrupt_geometry() {
+        let object = Object { geometry: Lazy::encoded(vec![0xff; 3]) };
+
+        assert!(object.geometry().is_none());
+
+        let mut yarn = Yarn::new();
+
+        object.tie(&mut yarn);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+
+        assert!(Object::untie(&mut yarn).is_none());
+    }
 }
@@ -0,0 +1,398 @@
+use std::cmp::Ordering;
+
+use super::geometry::{Geometry, Topology};
+use super::math::{self, Vec3};
+
+// The LRU cache size Forsyth's scoring is tuned for, independent of the hardware being modelled.
+const SCORE_CACHE_SIZE: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexCacheStats {
+    cache_size: usize,
+    triangles: usize,
+    vertices: usize,
+    misses: usize
+}
+
+impl VertexCacheStats {
+    pub fn cache_size(&self) -> usize {
+        self.cache_size
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    // Average cache miss ratio: transformed vertices per triangle.
+    pub fn acmr(&self) -> f32 {
+        if self.triangles == 0 { 0.0 } else { self.misses as f32 / self.triangles as f32 }
+    }
+
+    // Average transform to vertex ratio: 1.0 when every vertex is transformed exactly once.
+    pub fn atvr(&self) -> f32 {
+        if self.vertices == 0 { 0.0 } else { self.misses as f32 / self.vertices as f32 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptimizationReport {
+    before: VertexCacheStats,
+    after: VertexCacheStats
+}
+
+impl OptimizationReport {
+    pub fn before(&self) -> &VertexCacheStats {
+        &self.before
+    }
+
+    pub fn after(&self) -> &VertexCacheStats {
+        &self.after
+    }
+}
+
+impl Geometry {
+    // Simulates a FIFO post-transform cache of `cache_size` vertices.
+    pub fn analyze_vertex_cache(&self, cache_size: usize) -> VertexCacheStats {
+        let (triangles, _) = self.vertex_triangles();
+        let misses = cache_misses(&triangles, cache_size);
+
+        let mut used: Vec<_> = triangles.iter().flat_map(|triangle| triangle.iter().cloned()).collect();
+        used.sort_unstable();
+        used.dedup();
+
+        VertexCacheStats {
+            cache_size,
+            triangles: triangles.len(),
+            vertices: used.len(),
+            misses: misses.iter().sum()
+        }
+    }
+
+    pub fn optimize_vertex_cache(&mut self) -> Option<()> {
+        if self.topology() != Topology::TriangleList {
+            return None;
+        }
+
+        let (triangles, len) = self.vertex_triangles();
+        let order = forsyth(&triangles, len);
+
+        self.reorder_triangles(&order);
+
+        Some(())
+    }
+
+    // Splits the triangle order where the cache starts over and puts clusters facing away from
+    // the mesh centre first, so they tend to occlude the rest from any viewpoint.
+    pub fn optimize_overdraw(&mut self, cache_size: usize) -> Option<()> {
+        if self.topology() != Topology::TriangleList {
+            return None;
+        }
+
+        let misses = cache_misses(&self.vertex_triangles().0, cache_size);
+
+        let mut clusters: Vec<Vec<usize>> = vec![];
+
+        for (t, &misses) in misses.iter().enumerate() {
+            if misses == 3 || clusters.is_empty() {
+                clusters.push(vec![]);
+            }
+
+            clusters.last_mut().unwrap().push(t);
+        }
+
+        let faces: Vec<(Vec3, Vec3)> = (0..misses.len()).map(|t| {
+            let p = |i: usize| self.vertices()[self.indices()[3 * t + i].0];
+            let normal = math::cross(math::sub(p(1), p(0)), math::sub(p(2), p(0)));
+            let centroid = math::scale(math::add(math::add(p(0), p(1)), p(2)), 1.0 / 3.0);
+
+            (normal, centroid)
+        }).collect();
+
+        let weighted_centroid = |triangles: &[usize]| {
+            let (sum, area) = triangles.iter().fold(((0.0, 0.0, 0.0), 0.0), |(sum, area), &t| {
+                let (normal, centroid) = faces[t];
+                let weight = math::length(normal);
+
+                (math::add(sum, math::scale(centroid, weight)), area + weight)
+            });
+
+            if area > 0.0 { math::scale(sum, 1.0 / area) } else { sum }
+        };
+
+        let all: Vec<_> = (0..faces.len()).collect();
+        let center = weighted_centroid(&all);
+
+        let keys: Vec<f32> = clusters.iter().map(|cluster| {
+            let normal = cluster.iter().fold((0.0, 0.0, 0.0), |sum, &t| math::add(sum, faces[t].0));
+
+            math::dot(math::sub(weighted_centroid(cluster), center), math::normalize(normal))
+        }).collect();
+
+        let mut order: Vec<_> = (0..clusters.len()).collect();
+        order.sort_by(|&a, &b| keys[b].partial_cmp(&keys[a]).unwrap_or(Ordering::Equal));
+
+        let triangles: Vec<_> = order.iter().flat_map(|&c| clusters[c].iter().cloned()).collect();
+        self.reorder_triangles(&triangles);
+
+        Some(())
+    }
+
+    pub fn optimize_vertex_fetch(&mut self) {
+        self.reorder_values();
+    }
+
+    pub fn optimize(&mut self, cache_size: usize, overdraw: bool) -> Option<OptimizationReport> {
+        let before = self.analyze_vertex_cache(cache_size);
+
+        self.optimize_vertex_cache()?;
+
+        if overdraw {
+            self.optimize_overdraw(cache_size)?;
+        }
+
+        self.optimize_vertex_fetch();
+
+        Some(
+            OptimizationReport {
+                before,
+                after: self.analyze_vertex_cache(cache_size)
+            }
+        )
+    }
+
+    // Triangles over unique vertices, along with the number of vertices.
+    fn vertex_triangles(&self) -> (Vec<[usize; 3]>, usize) {
+        let (vertices, firsts) = self.corner_vertices();
+
+        let triangles = self.triangles().iter().map(|triangle| {
+            [vertices[triangle[0]], vertices[triangle[1]], vertices[triangle[2]]]
+        }).collect();
+
+        (triangles, firsts.len())
+    }
+
+    fn reorder_triangles(&mut self, triangles: &[usize]) {
+        let corners: Vec<_> = triangles.iter().flat_map(|&t| vec![3 * t, 3 * t + 1, 3 * t + 2]).collect();
+
+        self.reorder_corners(&corners);
+    }
+}
+
+fn cache_misses(triangles: &[[usize; 3]], cache_size: usize) -> Vec<usize> {
+    let len = triangles.iter().flat_map(|triangle| triangle.iter()).max().map_or(0, |&v| v + 1);
+
+    // A vertex is cached while fewer than `cache_size` misses have happened since its own.
+    let mut entered: Vec<Option<usize>> = vec![None; len];
+    let mut misses = 0;
+
+    triangles.iter().map(|triangle| {
+        let before = misses;
+
+        for &v in triangle {
+            let cached = entered[v].is_some_and(|time| misses - time < cache_size);
+
+            if !cached {
+                entered[v] = Some(misses);
+                misses += 1;
+            }
+        }
+
+        misses - before
+    }).collect()
+}
+
+fn vertex_score(position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+
+    let cache = match position {
+        Some(p) if p < 3 => 0.75,
+        Some(p) if p < SCORE_CACHE_SIZE => {
+            (1.0 - (p - 3) as f32 / (SCORE_CACHE_SIZE - 3) as f32).powf(1.5)
+        }
+        _ => 0.0
+    };
+
+    cache + 2.0 / (remaining as f32).sqrt()
+}
+
+// Tom Forsyth's "Linear-Speed Vertex Cache Optimisation": greedily emits the triangle whose
+// vertices score highest given their position in a modelled LRU cache and how many of their
+// triangles are still pending.
+fn forsyth(triangles: &[[usize; 3]], len: usize) -> Vec<usize> {
+    let mut adjacency = vec![vec![]; len];
+
+    for (t, triangle) in triangles.iter().enumerate() {
+        for &v in triangle {
+            adjacency[v].push(t);
+        }
+    }
+
+    let mut scores: Vec<f32> = adjacency.iter().map(|triangles| vertex_score(None, triangles.len())).collect();
+    let triangle_score = |scores: &[f32], t: usize| triangles[t].iter().map(|&v| scores[v]).sum::<f32>();
+    let mut triangle_scores: Vec<f32> = (0..triangles.len()).map(|t| triangle_score(&scores, t)).collect();
+
+    let mut emitted = vec![false; triangles.len()];
+    let mut cache: Vec<usize> = vec![];
+    let mut order = vec![];
+
+    let best_remaining = |emitted: &[bool], triangle_scores: &[f32]| {
+        (0..triangles.len()).filter(|&t| !emitted[t]).fold(None, |best: Option<usize>, t| {
+            match best {
+                Some(b) if triangle_scores[b] >= triangle_scores[t] => Some(b),
+                _ => Some(t)
+            }
+        })
+    };
+
+    let mut next = best_remaining(&emitted, &triangle_scores);
+
+    while let Some(t) = next {
+        order.push(t);
+        emitted[t] = true;
+
+        for &v in &triangles[t] {
+            adjacency[v].retain(|&other| other != t);
+        }
+
+        let mut updated = vec![];
+
+        for &v in triangles[t].iter().chain(cache.iter()) {
+            if !updated.contains(&v) {
+                updated.push(v);
+            }
+        }
+
+        let evicted = updated.split_off(updated.len().min(SCORE_CACHE_SIZE));
+
+        for (p, &v) in updated.iter().enumerate() {
+            scores[v] = vertex_score(Some(p), adjacency[v].len());
+        }
+
+        for &v in &evicted {
+            scores[v] = vertex_score(None, adjacency[v].len());
+        }
+
+        next = None;
+        let mut best = f32::NEG_INFINITY;
+
+        for &v in updated.iter().chain(evicted.iter()) {
+            for &other in &adjacency[v] {
+                triangle_scores[other] = triangle_score(&scores, other);
+
+                if triangle_scores[other] > best {
+                    best = triangle_scores[other];
+                    next = Some(other);
+                }
+            }
+        }
+
+        cache = updated;
+
+        if next.is_none() {
+            next = best_remaining(&emitted, &triangle_scores);
+        }
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An n by n grid of quads with its triangles in a scrambled order.
+    fn grid(n: usize) -> Geometry {
+        let vertices = (0..(n + 1) * (n + 1)).map(|i| ((i % (n + 1)) as f32, (i / (n + 1)) as f32, 0.0)).collect();
+
+        let mut triangles: Vec<_> = (0..n * n).flat_map(|q| {
+            let v = q / n * (n + 1) + q % n;
+            vec![[v, v + 1, v + n + 2], [v, v + n + 2, v + n + 1]]
+        }).collect();
+
+        let mut state = 12345u32;
+
+        for i in (1..triangles.len()).rev() {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            triangles.swap(i, (state >> 8) as usize % (i + 1));
+        }
+
+        let indices = triangles.iter().flat_map(|triangle| triangle.iter().map(|&v| (v, 0))).collect();
+
+        Geometry::new(vertices, vec![(0.0, 0.0)], indices).unwrap()
+    }
+
+    fn sorted_triangles(geometry: &Geometry) -> Vec<Vec<(f32, f32, f32)>> {
+        let mut triangles: Vec<_> = geometry.indices().chunks(3).map(|triangle| {
+            triangle.iter().map(|&(i, _)| geometry.vertices()[i]).collect::<Vec<_>>()
+        }).collect();
+
+        triangles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        triangles
+    }
+
+    #[test]
+    fn vertex_cache() {
+        let mut geometry = grid(16);
+        let triangles = sorted_triangles(&geometry);
+
+        let before = geometry.analyze_vertex_cache(16);
+        geometry.optimize_vertex_cache().unwrap();
+        let after = geometry.analyze_vertex_cache(16);
+
+        assert!(before.acmr() > 1.5);
+        assert!(after.acmr() < 0.8);
+        assert!(after.atvr() < before.atvr());
+        assert_eq!(sorted_triangles(&geometry), triangles);
+    }
+
+    #[test]
+    fn vertex_fetch() {
+        let mut geometry = grid(4).with_normals(vec![(0.0, 0.0, 1.0), (0.0, 0.0, -1.0)], vec![1; 96]).unwrap();
+        let expanded = geometry.expand();
+
+        geometry.optimize_vertex_fetch();
+
+        let mut next = 0;
+
+        for &(i, _) in geometry.indices() {
+            assert!(i <= next);
+            next = next.max(i + 1);
+        }
+
+        assert_eq!(geometry.normals().unwrap(), &[(0.0, 0.0, -1.0), (0.0, 0.0, 1.0)]);
+        assert_eq!(geometry.expand().vertices(), expanded.vertices());
+        assert_eq!(geometry.expand().normals(), expanded.normals());
+    }
+
+    #[test]
+    fn overdraw() {
+        // Two quads facing +z, the nearer one to a viewer above given last.
+        let quad = |z: f32, v: usize| {
+            (vec![(0.0, 0.0, z), (1.0, 0.0, z), (1.0, 1.0, z), (0.0, 1.0, z)],
+             vec![v, v + 1, v + 2, v, v + 2, v + 3])
+        };
+        let (mut vertices, mut indices) = quad(0.0, 0);
+        let (top_vertices, top_indices) = quad(1.0, 4);
+
+        vertices.extend(top_vertices);
+        indices.extend(top_indices);
+
+        let mut geometry = Geometry::new(
+            vertices,
+            vec![(0.0, 0.0)],
+            indices.into_iter().map(|i| (i, 0)).collect()
+        ).unwrap();
+
+        let report = geometry.optimize(16, true).unwrap();
+
+        assert!(report.after().acmr() <= report.before().acmr());
+        assert_eq!(geometry.vertices()[geometry.indices()[0].0].2, 1.0);
+        assert_eq!(geometry.vertices()[geometry.indices()[6].0].2, 0.0);
+
+        let mut strip = Geometry::with_topology(Topology::TriangleStrip, vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); 3]).unwrap();
+
+        assert!(strip.optimize(16, false).is_none());
+    }
+}
@@ -0,0 +1,294 @@
+use std::slice;
+
+use super::geometry::{self, Geometry};
+use super::math::{self, Vec2, Vec3};
+
+#[derive(Clone, Debug)]
+pub struct PolygonMesh {
+    vertices: Vec<(f32, f32, f32)>,
+    uvs: Vec<(f32, f32)>,
+    face_sizes: Vec<usize>,
+    indices: Vec<(usize, usize)>
+}
+
+impl PolygonMesh {
+    pub fn new(
+        vertices: Vec<(f32, f32, f32)>,
+        uvs: Vec<(f32, f32)>,
+        face_sizes: Vec<usize>,
+        indices: Vec<(usize, usize)>
+    ) -> Option<PolygonMesh> {
+        if !vertices.iter().all(geometry::is_finite3) || !uvs.iter().all(geometry::is_finite2) {
+            return None;
+        }
+
+        if indices.iter().any(|&(i, j)| i >= vertices.len() || j >= uvs.len()) {
+            return None;
+        }
+
+        if face_sizes.iter().any(|&size| size < 3) ||
+            face_sizes.iter().sum::<usize>() != indices.len() {
+            return None;
+        }
+
+        Some(
+            PolygonMesh {
+                vertices,
+                uvs,
+                face_sizes,
+                indices
+            }
+        )
+    }
+
+    pub fn vertices(&self) -> &[(f32, f32, f32)] {
+        &self.vertices[..]
+    }
+
+    pub fn uvs(&self) -> &[(f32, f32)] {
+        &self.uvs[..]
+    }
+
+    pub fn face_sizes(&self) -> &[usize] {
+        &self.face_sizes[..]
+    }
+
+    pub fn indices(&self) -> &[(usize, usize)] {
+        &self.indices[..]
+    }
+
+    pub fn len_faces(&self) -> usize {
+        self.face_sizes.len()
+    }
+
+    pub fn faces<'a>(&'a self) -> Faces<'a> {
+        Faces {
+            sizes: self.face_sizes.iter(),
+            indices: &self.indices[..]
+        }
+    }
+
+    pub fn triangulate(&self) -> Geometry {
+        let mut indices = vec![];
+
+        for face in self.faces() {
+            let positions: Vec<_> = face.iter().map(|&(i, _)| self.vertices[i]).collect();
+
+            for triangle in triangulate_face(&positions) {
+                indices.extend(triangle.iter().map(|&corner| face[corner]));
+            }
+        }
+
+        Geometry::new(self.vertices.clone(), self.uvs.clone(), indices).unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub struct Faces<'a> {
+    sizes: slice::Iter<'a, usize>,
+    indices: &'a [(usize, usize)]
+}
+
+impl<'a> Iterator for Faces<'a> {
+    type Item = &'a [(usize, usize)];
+
+    fn next(&mut self) -> Option<&'a [(usize, usize)]> {
+        let size = *self.sizes.next()?;
+        let (face, rest) = self.indices.split_at(size);
+
+        self.indices = rest;
+
+        Some(face)
+    }
+}
+
+// Ear clipping in the plane of the face's Newell normal, so non-planar and arbitrarily oriented
+// faces triangulate consistently with their winding. Faces with no area fall back to a fan.
+fn triangulate_face(positions: &[Vec3]) -> Vec<[usize; 3]> {
+    let fan = || (1..positions.len() - 1).map(|i| [0, i, i + 1]).collect();
+
+    if positions.len() == 3 {
+        return fan();
+    }
+
+    let origin = positions[0];
+    let normal = (0..positions.len()).fold((0.0, 0.0, 0.0), |sum, i| {
+        let a = math::sub(positions[i], origin);
+        let b = math::sub(positions[(i + 1) % positions.len()], origin);
+
+        math::add(sum, math::cross(a, b))
+    });
+
+    if math::length(normal) == 0.0 {
+        return fan();
+    }
+
+    let normal = math::normalize(normal);
+    let (nx, ny, nz) = normal;
+    let axis = if nx.abs() <= ny.abs() && nx.abs() <= nz.abs() {
+        (1.0, 0.0, 0.0)
+    } else if ny.abs() <= nz.abs() {
+        (0.0, 1.0, 0.0)
+    } else {
+        (0.0, 0.0, 1.0)
+    };
+    let u = math::normalize(math::cross(axis, normal));
+    let v = math::cross(normal, u);
+
+    let points: Vec<Vec2> = positions.iter().map(|&p| {
+        let p = math::sub(p, origin);
+        (math::dot(p, u), math::dot(p, v))
+    }).collect();
+
+    let mut remaining: Vec<usize> = (0..positions.len()).collect();
+    let mut triangles = vec![];
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let corners = |i: usize| (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]);
+
+        let is_convex = |i: usize| {
+            let (a, b, c) = corners(i);
+            math::cross2(math::sub2(points[b], points[a]), math::sub2(points[c], points[b])) > 0.0
+        };
+
+        let is_ear = |i: usize| {
+            let (a, b, c) = corners(i);
+
+            is_convex(i) && remaining.iter().all(|&other| {
+                let p = points[other];
+
+                other == a || other == b || other == c ||
+                    p == points[a] || p == points[b] || p == points[c] ||
+                    !in_triangle(p, points[a], points[b], points[c])
+            })
+        };
+
+        // Self-intersecting or degenerate faces may have no proper ear left; clip a convex
+        // corner, or any corner, rather than give up.
+        let ear = (0..len).find(|&i| is_ear(i))
+            .or_else(|| (0..len).find(|&i| is_convex(i)))
+            .unwrap_or(0);
+
+        let (a, b, c) = corners(ear);
+
+        triangles.push([a, b, c]);
+        remaining.remove(ear);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    math::cross2(math::sub2(b, a), math::sub2(p, a)) >= 0.0 &&
+        math::cross2(math::sub2(c, b), math::sub2(p, b)) >= 0.0 &&
+        math::cross2(math::sub2(a, c), math::sub2(p, c)) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(geometry: &Geometry) -> Vec3 {
+        geometry.indices().chunks(3).fold((0.0, 0.0, 0.0), |sum, triangle| {
+            let a = geometry.vertices()[triangle[0].0];
+            let b = geometry.vertices()[triangle[1].0];
+            let c = geometry.vertices()[triangle[2].0];
+
+            math::add(sum, math::scale(math::cross(math::sub(b, a), math::sub(c, a)), 0.5))
+        })
+    }
+
+    fn assert_faces_along(geometry: &Geometry, normal: Vec3) {
+        for triangle in geometry.indices().chunks(3) {
+            let a = geometry.vertices()[triangle[0].0];
+            let b = geometry.vertices()[triangle[1].0];
+            let c = geometry.vertices()[triangle[2].0];
+
+            assert!(math::dot(math::cross(math::sub(b, a), math::sub(c, a)), normal) > 0.0);
+        }
+    }
+
+    #[test]
+    fn new_invalid() {
+        let vertices = || vec![(0.0, 0.0, 0.0); 4];
+
+        assert!(PolygonMesh::new(vertices(), vec![(0.0, 0.0)], vec![4], vec![(0, 0); 4]).is_some());
+        assert!(PolygonMesh::new(vertices(), vec![(0.0, 0.0)], vec![2, 2], vec![(0, 0); 4]).is_none());
+        assert!(PolygonMesh::new(vertices(), vec![(0.0, 0.0)], vec![3], vec![(0, 0); 4]).is_none());
+        assert!(PolygonMesh::new(vertices(), vec![(0.0, 0.0)], vec![3], vec![(4, 0); 3]).is_none());
+    }
+
+    #[test]
+    fn quads_and_triangles() {
+        let mesh = PolygonMesh::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0), (2.0, 0.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            vec![4, 3],
+            vec![(0, 0), (1, 1), (2, 2), (3, 3), (1, 0), (4, 1), (2, 2)]
+        ).unwrap();
+
+        let faces: Vec<_> = mesh.faces().collect();
+
+        assert_eq!(faces.len(), 2);
+        assert_eq!(faces[1], &[(1, 0), (4, 1), (2, 2)]);
+
+        let geometry = mesh.triangulate();
+
+        assert_eq!(geometry.indices().len(), 9);
+        assert_eq!(&geometry.indices()[6..], &[(1, 0), (4, 1), (2, 2)]);
+        assert_eq!(area(&geometry), (0.0, 0.0, 1.5));
+    }
+
+    #[test]
+    fn concave() {
+        // An L shape whose reflex corner rules out a plain fan from the first vertex.
+        let mesh = PolygonMesh::new(
+            vec![(1.0, 1.0, 0.0), (1.0, 2.0, 0.0), (0.0, 2.0, 0.0), (0.0, 0.0, 0.0), (2.0, 0.0, 0.0), (2.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![6],
+            (0..6).map(|i| (i, 0)).collect()
+        ).unwrap();
+
+        let geometry = mesh.triangulate();
+
+        assert_eq!(geometry.indices().len(), 12);
+        assert_eq!(area(&geometry), (0.0, 0.0, 3.0));
+        assert_faces_along(&geometry, (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn projected() {
+        // A concave pentagon tilted out of every axis plane and wound clockwise within it.
+        let (u, v) = (math::normalize((1.0, 1.0, 0.0)), math::normalize((-1.0, 1.0, 2.0)));
+        let normal = math::cross(v, u);
+        let flat = [(0.0, 0.0), (0.0, 2.0), (1.0, 1.0), (2.0, 2.0), (2.0, 0.0)];
+
+        let mesh = PolygonMesh::new(
+            flat.iter().map(|&(x, y)| math::add(math::scale(u, x), math::scale(v, y))).collect(),
+            vec![(0.0, 0.0)],
+            vec![5],
+            (0..5).map(|i| (i, 0)).collect()
+        ).unwrap();
+
+        let geometry = mesh.triangulate();
+
+        assert_eq!(geometry.indices().len(), 9);
+        assert!((math::dot(area(&geometry), normal) - 3.0).abs() < 1e-5);
+        assert_faces_along(&geometry, normal);
+    }
+
+    #[test]
+    fn degenerate() {
+        let mesh = PolygonMesh::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (2.0, 0.0, 0.0), (3.0, 0.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![4],
+            (0..4).map(|i| (i, 0)).collect()
+        ).unwrap();
+
+        assert_eq!(mesh.triangulate().indices().len(), 6);
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use super::geometry::{Geometry, GeometryExpanded};
+use super::math::{self, Vec2, Vec3};
+use super::weld::WeldOptions;
+
+// Corners are collected with their own UVs and normals and welded at the end, so closed shapes
+// share their positions across UV seams and hard edges.
+struct Builder {
+    positions: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    corners: Vec<usize>
+}
+
+impl Builder {
+    fn new() -> Builder {
+        Builder {
+            positions: vec![],
+            uvs: vec![],
+            normals: vec![],
+            corners: vec![]
+        }
+    }
+
+    fn vertex(&mut self, position: Vec3, uv: Vec2, normal: Vec3) -> usize {
+        self.positions.push(position);
+        self.uvs.push(uv);
+        self.normals.push(normal);
+
+        self.positions.len() - 1
+    }
+
+    fn triangle(&mut self, a: usize, b: usize, c: usize) {
+        self.corners.extend_from_slice(&[a, b, c]);
+    }
+
+    // Counter-clockwise seen from the front.
+    fn quad(&mut self, a: usize, b: usize, c: usize, d: usize) {
+        self.triangle(a, b, c);
+        self.triangle(a, c, d);
+    }
+
+    // Revolves a profile, listed from bottom to top, around the Y axis. Each point is its distance
+    // from the axis, its height, its normal in the same two terms and its V coordinate. Points on
+    // the axis only get the triangles that are not degenerate.
+    fn lathe(&mut self, profile: &[(f32, f32, Vec2, f32)], segments: usize) {
+        let rows: Vec<Vec<usize>> = profile.iter().map(|&(radius, y, (normal_radius, normal_y), v)| {
+            (0..segments + 1).map(|i| {
+                let (cos, sin) = around(i, segments);
+                let position = (radius * cos, y, -radius * sin);
+                let normal = math::normalize((normal_radius * cos, normal_y, -normal_radius * sin));
+
+                self.vertex(position, (i as f32 / segments as f32, v), normal)
+            }).collect()
+        }).collect();
+
+        for j in 0..profile.len() - 1 {
+            for i in 0..segments {
+                let (a, b, c, d) = (rows[j][i], rows[j][i + 1], rows[j + 1][i + 1], rows[j + 1][i]);
+
+                if profile[j].0 != 0.0 {
+                    self.triangle(a, b, c);
+                }
+
+                if profile[j + 1].0 != 0.0 {
+                    self.triangle(a, c, d);
+                }
+            }
+        }
+    }
+
+    // A disc at height `y` facing up or down, with the same ring positions as `lathe`.
+    fn cap(&mut self, radius: f32, y: f32, up: bool, segments: usize) {
+        let normal = (0.0, if up { 1.0 } else { -1.0 }, 0.0);
+        let center = self.vertex((0.0, y, 0.0), (0.5, 0.5), normal);
+        let ring: Vec<_> = (0..segments).map(|i| {
+            let (cos, sin) = around(i, segments);
+            let v = if up { sin } else { -sin };
+            let uv = (0.5 + cos * 0.5, 0.5 + v * 0.5);
+
+            self.vertex((radius * cos, y, -radius * sin), uv, normal)
+        }).collect();
+
+        for i in 0..segments {
+            let (a, b) = (ring[i], ring[(i + 1) % segments]);
+
+            if up {
+                self.triangle(center, a, b);
+            } else {
+                self.triangle(center, b, a);
+            }
+        }
+    }
+
+    // `None` when sizes overflow into infinite positions or normals.
+    fn build(self) -> Option<Geometry> {
+        let vertices = self.corners.iter().map(|&corner| self.positions[corner]).collect();
+        let uvs = self.corners.iter().map(|&corner| self.uvs[corner]).collect();
+        let normals = self.corners.iter().map(|&corner| self.normals[corner]).collect();
+
+        let expanded = GeometryExpanded::new(vertices, uvs)?.with_normals(normals)?;
+
+        Some(expanded.weld(&WeldOptions::new()).0)
+    }
+}
+
+// The `i`th of `segments` directions around the Y axis. The last wraps onto the first exactly,
+// so seams weld.
+fn around(i: usize, segments: usize) -> (f32, f32) {
+    let angle = 2.0 * PI * (i % segments) as f32 / segments as f32;
+
+    (angle.cos(), angle.sin())
+}
+
+fn positive(value: f32) -> bool {
+    value > 0.0 && value.is_finite()
+}
+
+impl Geometry {
+    // Centered on the origin, with each face mapped to the whole UV square.
+    pub fn cube(size: f32) -> Option<Geometry> {
+        if !positive(size) {
+            return None;
+        }
+
+        let half = size / 2.0;
+        let faces = [
+            ((1.0, 0.0, 0.0), (0.0, 0.0, -1.0), (0.0, 1.0, 0.0)),
+            ((-1.0, 0.0, 0.0), (0.0, 0.0, 1.0), (0.0, 1.0, 0.0)),
+            ((0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, -1.0)),
+            ((0.0, -1.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0)),
+            ((0.0, 0.0, 1.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)),
+            ((0.0, 0.0, -1.0), (-1.0, 0.0, 0.0), (0.0, 1.0, 0.0))
+        ];
+        let mut builder = Builder::new();
+
+        for &(normal, u, v) in &faces {
+            let corner = |su: f32, sv: f32| math::scale(math::add(normal, math::add(math::scale(u, su), math::scale(v, sv))), half);
+
+            let a = builder.vertex(corner(-1.0, -1.0), (0.0, 0.0), normal);
+            let b = builder.vertex(corner(1.0, -1.0), (1.0, 0.0), normal);
+            let c = builder.vertex(corner(1.0, 1.0), (1.0, 1.0), normal);
+            let d = builder.vertex(corner(-1.0, 1.0), (0.0, 1.0), normal);
+
+            builder.quad(a, b, c, d);
+        }
+
+        builder.build()
+    }
+
+    // In the XZ plane facing up, centered on the origin, split into a grid of quads.
+    pub fn plane(width: f32, depth: f32, x_segments: usize, z_segments: usize) -> Option<Geometry> {
+        if !positive(width) || !positive(depth) || x_segments == 0 || z_segments == 0 {
+            return None;
+        }
+
+        let mut builder = Builder::new();
+        let rows: Vec<Vec<usize>> = (0..z_segments + 1).map(|j| {
+            (0..x_segments + 1).map(|i| {
+                let (u, v) = (i as f32 / x_segments as f32, j as f32 / z_segments as f32);
+
+                builder.vertex(((u - 0.5) * width, 0.0, (0.5 - v) * depth), (u, v), (0.0, 1.0, 0.0))
+            }).collect()
+        }).collect();
+
+        for j in 0..z_segments {
+            for i in 0..x_segments {
+                builder.quad(rows[j][i], rows[j][i + 1], rows[j + 1][i + 1], rows[j + 1][i]);
+            }
+        }
+
+        builder.build()
+    }
+
+    // Segments go around the Y axis and rings from pole to pole.
+    pub fn uv_sphere(radius: f32, segments: usize, rings: usize) -> Option<Geometry> {
+        if !positive(radius) || segments < 3 || rings < 2 {
+            return None;
+        }
+
+        let profile: Vec<_> = (0..rings + 1).map(|j| {
+            let v = j as f32 / rings as f32;
+            let (sin, cos) = (PI * v).sin_cos();
+
+            // The poles are placed exactly so that their corners weld.
+            if j == 0 || j == rings {
+                (0.0, -cos.signum() * radius, (0.0, -cos.signum()), v)
+            } else {
+                (sin * radius, -cos * radius, (sin, -cos), v)
+            }
+        }).collect();
+
+        let mut builder = Builder::new();
+        builder.lathe(&profile, segments);
+
+        builder.build()
+    }
+
+    // An icosahedron whose triangles are each split in four `subdivisions` times, with the same
+    // UV mapping as the UV sphere.
+    pub fn icosphere(radius: f32, subdivisions: usize) -> Option<Geometry> {
+        if !positive(radius) || subdivisions > 8 {
+            return None;
+        }
+
+        let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+        let mut points: Vec<Vec3> = vec![
+            (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+            (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+            (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0)
+        ].into_iter().map(math::normalize).collect();
+        let mut triangles = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1]
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, points: &mut Vec<Vec3>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    points.push(math::normalize(math::add(points[a], points[b])));
+                    points.len() - 1
+                })
+            };
+
+            triangles = triangles.into_iter().flat_map(|triangle| {
+                let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+                let ab = midpoint(a, b, &mut points);
+                let bc = midpoint(b, c, &mut points);
+                let ca = midpoint(c, a, &mut points);
+
+                vec![[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            }).collect();
+        }
+
+        let mut builder = Builder::new();
+
+        for triangle in triangles {
+            let mut uvs: Vec<Vec2> = triangle.iter().map(|&i| {
+                let (x, y, z) = points[i];
+                let u = (-z).atan2(x) / (2.0 * PI);
+                (if u < 0.0 { u + 1.0 } else { u }, (-y).clamp(-1.0, 1.0).acos() / PI)
+            }).collect();
+
+            // Triangles across the seam take their small U coordinates past 1.
+            if uvs.iter().any(|uv| uv.0 > 0.75) && uvs.iter().any(|uv| uv.0 < 0.25) {
+                for uv in uvs.iter_mut().filter(|uv| uv.0 < 0.5) {
+                    uv.0 += 1.0;
+                }
+            }
+
+            // U is meaningless at the poles, so those corners take the others' mean.
+            for k in 0..3 {
+                let (x, _, z) = points[triangle[k]];
+
+                if x == 0.0 && z == 0.0 {
+                    uvs[k].0 = (uvs[(k + 1) % 3].0 + uvs[(k + 2) % 3].0) / 2.0;
+                }
+            }
+
+            let corners: Vec<_> = (0..3).map(|k| {
+                let point = points[triangle[k]];
+                builder.vertex(math::scale(point, radius), uvs[k], point)
+            }).collect();
+
+            builder.triangle(corners[0], corners[1], corners[2]);
+        }
+
+        builder.build()
+    }
+
+    // Centered on the origin along the Y axis, with capped ends.
+    pub fn cylinder(radius: f32, height: f32, segments: usize) -> Option<Geometry> {
+        if !positive(radius) || !positive(height) || segments < 3 {
+            return None;
+        }
+
+        let half = height / 2.0;
+        let mut builder = Builder::new();
+
+        builder.lathe(&[(radius, -half, (1.0, 0.0), 0.0), (radius, half, (1.0, 0.0), 1.0)], segments);
+        builder.cap(radius, -half, false, segments);
+        builder.cap(radius, half, true, segments);
+
+        builder.build()
+    }
+
+    // Centered on the origin along the Y axis with its apex on top and a capped base.
+    pub fn cone(radius: f32, height: f32, segments: usize) -> Option<Geometry> {
+        if !positive(radius) || !positive(height) || segments < 3 {
+            return None;
+        }
+
+        let half = height / 2.0;
+        let slope = (height, radius);
+        let mut builder = Builder::new();
+
+        builder.lathe(&[(radius, -half, slope, 0.0), (0.0, half, slope, 1.0)], segments);
+        builder.cap(radius, -half, false, segments);
+
+        builder.build()
+    }
+
+    // Around the Y axis, with U following the ring and V the tube.
+    pub fn torus(
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: usize,
+        minor_segments: usize
+    ) -> Option<Geometry> {
+        if !positive(minor_radius) || !major_radius.is_finite() || major_radius <= minor_radius {
+            return None;
+        }
+
+        if major_segments < 3 || minor_segments < 3 {
+            return None;
+        }
+
+        let profile: Vec<_> = (0..minor_segments + 1).map(|j| {
+            let (cos, sin) = around(j, minor_segments);
+            (major_radius + minor_radius * cos, minor_radius * sin, (cos, sin), j as f32 / minor_segments as f32)
+        }).collect();
+
+        let mut builder = Builder::new();
+        builder.lathe(&profile, major_segments);
+
+        builder.build()
+    }
+
+    // A cylinder of `height` between two hemispheres, centered on the origin along the Y axis.
+    // V runs along the outline in proportion to its length.
+    pub fn capsule(radius: f32, height: f32, segments: usize, rings: usize) -> Option<Geometry> {
+        if !positive(radius) || !positive(height) || segments < 3 || rings < 1 {
+            return None;
+        }
+
+        let half = height / 2.0;
+        let length = PI * radius + height;
+        let quarter = PI * radius / 2.0;
+
+        let bottom = (0..rings + 1).map(|j| {
+            let (sin, cos) = (PI / 2.0 * j as f32 / rings as f32).sin_cos();
+            let v = quarter * j as f32 / rings as f32 / length;
+
+            if j == 0 {
+                (0.0, -half - radius, (0.0, -1.0), v)
+            } else {
+                (sin * radius, -half - cos * radius, (sin, -cos), v)
+            }
+        });
+        let top = (0..rings + 1).map(|j| {
+            let (sin, cos) = (PI / 2.0 * j as f32 / rings as f32).sin_cos();
+            let v = (quarter + height + quarter * j as f32 / rings as f32) / length;
+
+            if j == rings {
+                (0.0, half + radius, (0.0, 1.0), v)
+            } else {
+                (cos * radius, half + sin * radius, (cos, sin), v)
+            }
+        });
+        let profile: Vec<_> = bottom.chain(top).collect();
+
+        let mut builder = Builder::new();
+        builder.lathe(&profile, segments);
+
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_closed(geometry: &Geometry, volume: f32, tolerance: f32) {
+        let report = geometry.validate();
+
+        assert!(report.is_valid(), "{:?}", report);
+        assert!(report.is_closed(), "{:?}", report.boundary_edges());
+        assert!((geometry.volume().unwrap() / volume - 1.0).abs() < tolerance, "{}", geometry.volume().unwrap());
+
+        // Normals lean the same way as their triangles.
+        let normals = geometry.normals().unwrap();
+        let normal_indices = geometry.normal_indices().unwrap();
+
+        for (t, triangle) in geometry.indices().chunks(3).enumerate() {
+            let position = |k: usize| geometry.vertices()[triangle[k].0];
+            let face = math::cross(math::sub(position(1), position(0)), math::sub(position(2), position(0)));
+
+            for k in 0..3 {
+                assert!(math::dot(face, normals[normal_indices[3 * t + k]]) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn cube() {
+        let cube = Geometry::cube(2.0).unwrap();
+
+        assert_eq!(cube.vertices().len(), 8);
+        assert_eq!(cube.uvs().len(), 4);
+        assert_eq!(cube.normals().unwrap().len(), 6);
+        check_closed(&cube, 8.0, 1e-6);
+        assert!(Geometry::cube(0.0).is_none());
+    }
+
+    #[test]
+    fn plane() {
+        let plane = Geometry::plane(4.0, 2.0, 4, 2).unwrap();
+        let report = plane.validate();
+
+        assert_eq!(plane.vertices().len(), 15);
+        assert_eq!(plane.indices().len(), 4 * 2 * 6);
+        assert_eq!(plane.normals().unwrap(), &[(0.0, 1.0, 0.0)]);
+        assert_eq!(plane.surface_area(), 8.0);
+        assert_eq!(plane.uv_area(), 1.0);
+        assert!(report.is_valid());
+        assert_eq!(report.boundary_edges().len(), 2 * (4 + 2));
+        assert!(Geometry::plane(1.0, 1.0, 0, 1).is_none());
+    }
+
+    #[test]
+    fn spheres() {
+        let sphere = Geometry::uv_sphere(2.0, 48, 24).unwrap();
+
+        assert_eq!(sphere.vertices().len(), 48 * 23 + 2);
+        check_closed(&sphere, 4.0 / 3.0 * PI * 8.0, 0.02);
+        assert!(sphere.uvs().iter().all(|&(u, v)| (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)));
+        assert!(sphere.vertices().iter().all(|&p| (math::length(p) - 2.0).abs() < 1e-5));
+
+        let icosphere = Geometry::icosphere(2.0, 3).unwrap();
+
+        assert_eq!(Geometry::icosphere(1.0, 0).unwrap().vertices().len(), 12);
+        assert_eq!(icosphere.vertices().len(), 642);
+        assert_eq!(icosphere.indices().len(), 20 * 64 * 3);
+        check_closed(&icosphere, 4.0 / 3.0 * PI * 8.0, 0.02);
+        assert!(icosphere.vertices().iter().all(|&p| (math::length(p) - 2.0).abs() < 1e-5));
+        assert!(Geometry::uv_sphere(1.0, 2, 2).is_none());
+    }
+
+    #[test]
+    fn solids_of_revolution() {
+        let cylinder = Geometry::cylinder(1.0, 3.0, 64).unwrap();
+        let cone = Geometry::cone(1.0, 3.0, 64).unwrap();
+        let torus = Geometry::torus(2.0, 0.5, 64, 32).unwrap();
+        let capsule = Geometry::capsule(1.0, 2.0, 64, 16).unwrap();
+
+        assert_eq!(cylinder.vertices().len(), 64 * 2 + 2);
+        assert_eq!(cone.vertices().len(), 64 + 2);
+        assert_eq!(torus.vertices().len(), 64 * 32);
+        assert_eq!(capsule.vertices().len(), 64 * 32 + 2);
+
+        check_closed(&cylinder, PI * 3.0, 0.01);
+        check_closed(&cone, PI, 0.01);
+        check_closed(&torus, 2.0 * PI * PI * 2.0 * 0.25, 0.01);
+        check_closed(&capsule, PI * 2.0 + 4.0 / 3.0 * PI, 0.01);
+
+        assert!(Geometry::torus(1.0, 1.0, 8, 8).is_none());
+        assert!(Geometry::capsule(1.0, 0.0, 8, 8).is_none());
+        assert!(Geometry::torus(3.0e38, 1.0e38, 8, 8).is_none());
+    }
+}
@@ -0,0 +1,510 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::geometry::{Geometry, Topology};
+use super::math::{self, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimplifyOptions {
+    target_ratio: f32,
+    max_error: f32,
+    lock_borders: bool
+}
+
+impl SimplifyOptions {
+    pub fn new() -> SimplifyOptions {
+        SimplifyOptions {
+            target_ratio: 0.5,
+            max_error: f32::INFINITY,
+            lock_borders: true
+        }
+    }
+
+    pub fn with_target_ratio(self, target_ratio: f32) -> Option<SimplifyOptions> {
+        if !(0.0..=1.0).contains(&target_ratio) {
+            return None;
+        }
+
+        Some(
+            SimplifyOptions {
+                target_ratio,
+                ..self
+            }
+        )
+    }
+
+    pub fn with_max_error(self, max_error: f32) -> Option<SimplifyOptions> {
+        if max_error.is_nan() || max_error < 0.0 {
+            return None;
+        }
+
+        Some(
+            SimplifyOptions {
+                max_error,
+                ..self
+            }
+        )
+    }
+
+    pub fn with_locked_borders(self, lock_borders: bool) -> SimplifyOptions {
+        SimplifyOptions {
+            lock_borders,
+            ..self
+        }
+    }
+
+    pub fn target_ratio(&self) -> f32 {
+        self.target_ratio
+    }
+
+    pub fn max_error(&self) -> f32 {
+        self.max_error
+    }
+
+    pub fn locks_borders(&self) -> bool {
+        self.lock_borders
+    }
+}
+
+impl Default for SimplifyOptions {
+    fn default() -> SimplifyOptions {
+        SimplifyOptions::new()
+    }
+}
+
+// Coefficients of the symmetric 4x4 matrix of a sum of squared plane distances.
+#[derive(Clone, Copy, Debug)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane((a, b, c): Vec3, point: Vec3, weight: f64) -> Quadric {
+        let (a, b, c) = (a as f64, b as f64, c as f64);
+        let d = -(a * point.0 as f64 + b * point.1 as f64 + c * point.2 as f64);
+
+        Quadric([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d]).scale(weight)
+    }
+
+    fn zero() -> Quadric {
+        Quadric([0.0; 10])
+    }
+
+    fn scale(self, s: f64) -> Quadric {
+        let mut q = self.0;
+
+        for x in q.iter_mut() {
+            *x *= s;
+        }
+
+        Quadric(q)
+    }
+
+    fn add(self, other: &Quadric) -> Quadric {
+        let mut q = self.0;
+
+        for (x, y) in q.iter_mut().zip(other.0.iter()) {
+            *x += *y;
+        }
+
+        Quadric(q)
+    }
+
+    fn error(&self, (x, y, z): Vec3) -> f64 {
+        let (x, y, z) = (x as f64, y as f64, z as f64);
+        let q = &self.0;
+
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x +
+            q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y +
+            q[7] * z * z + 2.0 * q[8] * z + q[9]
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    versions: (usize, usize)
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Collapse) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so the heap pops the cheapest collapse first.
+impl Ord for Collapse {
+    fn cmp(&self, other: &Collapse) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Simplifier<'a> {
+    geometry: &'a Geometry,
+    triangles: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    // Every corner's position and the corner whose other indices it now uses.
+    positions: Vec<usize>,
+    sources: Vec<usize>,
+    incident: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    versions: Vec<usize>
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(geometry: &'a Geometry, lock_borders: bool) -> Simplifier<'a> {
+        let len = geometry.vertices().len();
+        let triangles = geometry.triangles();
+        let positions: Vec<_> = geometry.indices().iter().map(|&(i, _)| i).collect();
+
+        let mut incident = vec![vec![]; len];
+        let mut quadrics = vec![Quadric::zero(); len];
+        let mut edges = HashMap::new();
+
+        for (t, triangle) in triangles.iter().enumerate() {
+            let p: Vec<_> = triangle.iter().map(|&corner| geometry.vertices()[positions[corner]]).collect();
+            let normal = math::normalize(math::cross(math::sub(p[1], p[0]), math::sub(p[2], p[0])));
+            let quadric = Quadric::plane(normal, p[0], 1.0);
+
+            for i in 0..3 {
+                let v = positions[triangle[i]];
+                let w = positions[triangle[(i + 1) % 3]];
+
+                incident[v].push(t);
+                quadrics[v] = quadrics[v].add(&quadric);
+                edges.entry((v.min(w), v.max(w))).or_insert(vec![]).push((t, i));
+            }
+        }
+
+        // Corners at a UV seam, or any other discontinuity, disagree on their other indices.
+        let wedge = |corner: usize| {
+            let (i, j) = geometry.indices()[corner];
+            let mut key = vec![j];
+
+            key.extend(geometry.normal_indices().map(|indices| indices[corner]));
+            key.extend(geometry.tangent_indices().map(|indices| indices[corner]));
+            key.extend(geometry.attributes().iter().map(|channel| channel.index(corner, (i, j))));
+
+            key
+        };
+
+        let mut wedges: Vec<Option<Vec<usize>>> = vec![None; len];
+        let mut locked = vec![false; len];
+
+        for (corner, &v) in positions.iter().enumerate() {
+            let key = wedge(corner);
+
+            match wedges[v] {
+                Some(ref existing) if *existing != key => locked[v] = true,
+                Some(_) => {}
+                None => wedges[v] = Some(key)
+            }
+        }
+
+        for (&(v, w), sides) in &edges {
+            if sides.len() != 1 {
+                continue;
+            }
+
+            if lock_borders {
+                locked[v] = true;
+                locked[w] = true;
+            } else {
+                // Keeps border vertices on the plane through the edge perpendicular to its face.
+                let (t, i) = sides[0];
+                let p = |i: usize| geometry.vertices()[positions[triangles[t][i % 3]]];
+                let edge = math::sub(p(i + 1), p(i));
+                let normal = math::cross(math::sub(p(1), p(0)), math::sub(p(2), p(0)));
+                let quadric = Quadric::plane(math::normalize(math::cross(edge, normal)), p(i), 1.0);
+
+                quadrics[v] = quadrics[v].add(&quadric);
+                quadrics[w] = quadrics[w].add(&quadric);
+            }
+        }
+
+        Simplifier {
+            geometry,
+            alive: vec![true; triangles.len()],
+            triangles,
+            sources: (0..positions.len()).collect(),
+            positions,
+            incident,
+            quadrics,
+            locked,
+            versions: vec![0; len]
+        }
+    }
+
+    fn position(&self, v: usize) -> Vec3 {
+        self.geometry.vertices()[v]
+    }
+
+    fn neighbors(&self, v: usize) -> Vec<usize> {
+        let mut neighbors = vec![];
+
+        for &t in &self.incident[v] {
+            for &corner in &self.triangles[t] {
+                let w = self.positions[corner];
+
+                if w != v && !neighbors.contains(&w) {
+                    neighbors.push(w);
+                }
+            }
+        }
+
+        neighbors
+    }
+
+    fn collapse(&self, from: usize, to: usize) -> Collapse {
+        Collapse {
+            cost: self.quadrics[from].add(&self.quadrics[to]).error(self.position(to)),
+            from,
+            to,
+            versions: (self.versions[from], self.versions[to])
+        }
+    }
+
+    // Rejects collapses that would flip or degenerate one of the triangles that stay.
+    fn is_valid(&self, from: usize, to: usize) -> bool {
+        if self.locked[from] {
+            return false;
+        }
+
+        self.incident[from].iter().all(|&t| {
+            let corners = self.triangles[t];
+            let p: Vec<_> = corners.iter().map(|&corner| self.positions[corner]).collect();
+
+            if p.contains(&to) {
+                return true;
+            }
+
+            let before: Vec<_> = p.iter().map(|&v| self.position(v)).collect();
+            let after: Vec<_> = p.iter().map(|&v| self.position(if v == from { to } else { v })).collect();
+
+            let normal = |p: &[Vec3]| math::cross(math::sub(p[1], p[0]), math::sub(p[2], p[0]));
+            let (before, after) = (normal(&before), normal(&after));
+
+            math::length(after) > 0.0 && math::dot(before, after) > 0.0
+        })
+    }
+
+    fn apply(&mut self, from: usize, to: usize) -> usize {
+        let shared = self.incident[from].iter().cloned().find(|&t| {
+            self.triangles[t].iter().any(|&corner| self.positions[corner] == to)
+        }).unwrap();
+        let reference = self.triangles[shared].iter().cloned().find(|&corner| {
+            self.positions[corner] == to
+        }).unwrap();
+
+        let mut removed = 0;
+        let incident = self.incident[from].clone();
+
+        for &t in &incident {
+            let corners = self.triangles[t];
+
+            if corners.iter().any(|&corner| self.positions[corner] == to) {
+                self.alive[t] = false;
+                removed += 1;
+
+                for &corner in &corners {
+                    let v = self.positions[corner];
+                    self.incident[v].retain(|&other| other != t);
+                }
+            } else {
+                for &corner in &corners {
+                    if self.positions[corner] == from {
+                        self.positions[corner] = to;
+                        self.sources[corner] = self.sources[reference];
+                    }
+                }
+
+                self.incident[to].push(t);
+            }
+        }
+
+        self.incident[from].clear();
+        self.quadrics[to] = self.quadrics[to].add(&self.quadrics[from]);
+        self.versions[from] += 1;
+        self.versions[to] += 1;
+
+        removed
+    }
+}
+
+impl Geometry {
+    // Half-edge collapses ordered by quadric error, so every vertex keeps its original position
+    // and UVs stay valid. Vertices on UV seams or other attribute discontinuities never move.
+    // Returns the simplified geometry and the largest error, as a distance, of any collapse.
+    pub fn simplify(&self, options: &SimplifyOptions) -> Option<(Geometry, f32)> {
+        if self.topology() != Topology::TriangleList {
+            return None;
+        }
+
+        let mut simplifier = Simplifier::new(self, options.lock_borders);
+        let mut heap = BinaryHeap::new();
+
+        for v in 0..self.vertices().len() {
+            for w in simplifier.neighbors(v) {
+                heap.push(simplifier.collapse(v, w));
+            }
+        }
+
+        let target = (options.target_ratio * simplifier.triangles.len() as f32).ceil() as usize;
+        let mut remaining = simplifier.triangles.len();
+        let mut error = 0.0f64;
+
+        while remaining > target {
+            let collapse = match heap.pop() {
+                Some(collapse) => collapse,
+                None => break
+            };
+            let (from, to) = (collapse.from, collapse.to);
+
+            if collapse.versions != (simplifier.versions[from], simplifier.versions[to]) {
+                if simplifier.neighbors(from).contains(&to) {
+                    heap.push(simplifier.collapse(from, to));
+                }
+
+                continue;
+            }
+
+            if collapse.cost.max(0.0).sqrt() > options.max_error as f64 {
+                break;
+            }
+
+            if !simplifier.is_valid(from, to) {
+                continue;
+            }
+
+            remaining -= simplifier.apply(from, to);
+            error = error.max(collapse.cost.max(0.0).sqrt());
+
+            for w in simplifier.neighbors(to) {
+                heap.push(simplifier.collapse(w, to));
+                heap.push(simplifier.collapse(to, w));
+            }
+        }
+
+        // Every source corner is at its corner's new position, so the result keeps this geometry's
+        // indices and only drops what the removed triangles used.
+        let triangles: Vec<_> = (0..simplifier.triangles.len())
+            .filter(|&t| simplifier.alive[t])
+            .map(|t| {
+                let corners = simplifier.triangles[t];
+                [simplifier.sources[corners[0]], simplifier.sources[corners[1]], simplifier.sources[corners[2]]]
+            })
+            .collect();
+
+        Some((self.with_triangles(&triangles), error as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::attribute::{Attribute, AttributeIndices, AttributeValues};
+
+    fn grid<F: Fn(usize, usize) -> f32>(n: usize, height: F, seam: bool) -> Geometry {
+        let vertices = (0..(n + 1) * (n + 1)).map(|i| {
+            let (x, y) = (i % (n + 1), i / (n + 1));
+            (x as f32, y as f32, height(x, y))
+        }).collect();
+
+        // Seam corners on the left half use a second copy of the UVs.
+        let mut uvs: Vec<_> = (0..(n + 1) * (n + 1)).map(|i| ((i % (n + 1)) as f32, (i / (n + 1)) as f32)).collect();
+        uvs.extend(uvs.clone());
+
+        let indices = (0..n * n).flat_map(|q| {
+            let v = q / n * (n + 1) + q % n;
+            let offset = if seam && q % n < n / 2 { (n + 1) * (n + 1) } else { 0 };
+
+            vec![v, v + 1, v + n + 2, v, v + n + 2, v + n + 1].into_iter().map(move |v| {
+                let uv = if v % (n + 1) == n / 2 { v + offset } else { v };
+                (v, uv)
+            })
+        }).collect();
+
+        Geometry::new(vertices, uvs, indices).unwrap()
+    }
+
+    fn area(geometry: &Geometry) -> f32 {
+        geometry.indices().chunks(3).map(|triangle| {
+            let p = |i: usize| geometry.vertices()[triangle[i].0];
+            math::length(math::cross(math::sub(p(1), p(0)), math::sub(p(2), p(0)))) / 2.0
+        }).sum()
+    }
+
+    #[test]
+    fn planar() {
+        let geometry = grid(8, |_, _| 0.0, false);
+        let options = SimplifyOptions::new().with_target_ratio(0.1).unwrap();
+
+        let (simplified, error) = geometry.simplify(&options).unwrap();
+
+        assert!(simplified.indices().len() / 3 < 40);
+        assert!(error < 1e-6);
+        assert!((area(&simplified) - 64.0).abs() < 1e-3);
+
+        for x in 0..9 {
+            assert!(simplified.vertices().contains(&(x as f32, 0.0, 0.0)));
+            assert!(simplified.vertices().contains(&(8.0, x as f32, 0.0)));
+        }
+
+        let (unlocked, _) = geometry.simplify(&options.with_locked_borders(false)).unwrap();
+
+        assert!(unlocked.indices().len() < simplified.indices().len());
+        assert!((area(&unlocked) - 64.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn seams() {
+        let geometry = grid(8, |_, _| 0.0, true);
+        let (simplified, _) = geometry.simplify(&SimplifyOptions::new().with_target_ratio(0.0).unwrap()).unwrap();
+
+        for y in 0..9 {
+            assert!(simplified.vertices().contains(&(4.0, y as f32, 0.0)));
+        }
+
+        assert!(simplified.uvs().iter().all(|&uv| geometry.uvs().contains(&uv)));
+    }
+
+    #[test]
+    fn max_error() {
+        let geometry = grid(8, |x, y| (x * x + y * y) as f32 * 0.1, false);
+
+        let options = SimplifyOptions::new().with_target_ratio(0.0).unwrap().with_max_error(0.0).unwrap();
+        let (simplified, error) = geometry.simplify(&options).unwrap();
+
+        assert_eq!(error, 0.0);
+        assert_eq!(simplified.indices().len(), geometry.indices().len());
+
+        let (simplified, error) = geometry.simplify(&options.with_max_error(1.0).unwrap()).unwrap();
+
+        assert!(error > 0.0 && error <= 1.0);
+        assert!(simplified.indices().len() < geometry.indices().len());
+        assert!(SimplifyOptions::new().with_target_ratio(1.5).is_none());
+    }
+
+    #[test]
+    fn index_structure() {
+        // Two triangles on coincident but distinct vertices, with equal UVs and weights.
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0); 6],
+            (0..6).map(|i| (i, i)).collect()
+        ).unwrap().with_attribute(
+            Attribute::new("weight", 1, AttributeValues::F32(vec![0.5; 6])).unwrap(),
+            AttributeIndices::Vertices
+        ).unwrap();
+
+        let (simplified, _) = geometry.simplify(&SimplifyOptions::new().with_target_ratio(1.0).unwrap()).unwrap();
+
+        assert_eq!(simplified.vertices(), geometry.vertices());
+        assert_eq!(simplified.uvs(), geometry.uvs());
+        assert_eq!(simplified.indices(), geometry.indices());
+        assert_eq!(simplified.attribute("weight").unwrap().indices(), &AttributeIndices::Vertices);
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::{HashMap, HashSet};
+
+use super::geometry::{Geometry, Topology};
+use super::math::{self, Vec2, Vec3};
+use super::polygon::PolygonMesh;
+
+impl PolygonMesh {
+    // Catmull-Clark, turning every face into quads `levels` times. Edges listed in `creases`, as
+    // pairs of positions, stay sharp like the boundaries, and vertices where three or more sharp
+    // edges meet stay put.
+    pub fn subdivide_catmull_clark(&self, levels: usize, creases: &[(usize, usize)]) -> Option<PolygonMesh> {
+        let faces: Vec<Vec<usize>> = self.faces().map(|face| face.iter().map(|&(i, _)| i).collect()).collect();
+        let uv_faces: Vec<Vec<usize>> = self.faces().map(|face| face.iter().map(|&(_, j)| j).collect()).collect();
+
+        let ((vertices, faces), (uvs, uv_faces)) = subdivide(
+            (self.vertices().to_vec(), faces),
+            (self.uvs().to_vec(), uv_faces),
+            levels,
+            creases,
+            catmull_clark,
+            catmull_clark
+        )?;
+
+        let indices = faces.iter().zip(&uv_faces).flat_map(|(face, uv_face)| {
+            face.iter().cloned().zip(uv_face.iter().cloned()).collect::<Vec<_>>()
+        }).collect();
+
+        PolygonMesh::new(vertices, uvs, faces.iter().map(|face| face.len()).collect(), indices)
+    }
+}
+
+impl Geometry {
+    // Loop, splitting every triangle in four `levels` times, with creases as in Catmull-Clark.
+    // Only positions and UVs are subdivided, so normals, tangents and attributes have to be made
+    // again afterwards.
+    pub fn subdivide_loop(&self, levels: usize, creases: &[(usize, usize)]) -> Option<Geometry> {
+        match self.topology() {
+            Topology::TriangleList | Topology::TriangleStrip => {}
+            _ => return None
+        }
+
+        let triangles = self.triangles();
+        let faces = triangles.iter().map(|triangle| triangle.iter().map(|&corner| self.indices()[corner].0).collect()).collect();
+        let uv_faces = triangles.iter().map(|triangle| triangle.iter().map(|&corner| self.indices()[corner].1).collect()).collect();
+
+        let ((vertices, faces), (uvs, uv_faces)) = subdivide(
+            (self.vertices().to_vec(), faces),
+            (self.uvs().to_vec(), uv_faces),
+            levels,
+            creases,
+            loop_level,
+            loop_level
+        )?;
+
+        let indices = faces.iter().zip(&uv_faces).flat_map(|(face, uv_face)| {
+            face.iter().cloned().zip(uv_face.iter().cloned()).collect::<Vec<_>>()
+        }).collect();
+
+        Geometry::new(vertices, uvs, indices)
+    }
+}
+
+trait Point: Copy {
+    fn zero() -> Self;
+    fn add(self, other: Self) -> Self;
+    fn scale(self, s: f32) -> Self;
+}
+
+impl Point for Vec3 {
+    fn zero() -> Vec3 {
+        (0.0, 0.0, 0.0)
+    }
+
+    fn add(self, other: Vec3) -> Vec3 {
+        math::add(self, other)
+    }
+
+    fn scale(self, s: f32) -> Vec3 {
+        math::scale(self, s)
+    }
+}
+
+impl Point for Vec2 {
+    fn zero() -> Vec2 {
+        (0.0, 0.0)
+    }
+
+    fn add(self, other: Vec2) -> Vec2 {
+        (self.0 + other.0, self.1 + other.1)
+    }
+
+    fn scale(self, s: f32) -> Vec2 {
+        (self.0 * s, self.1 * s)
+    }
+}
+
+type Mesh<T> = (Vec<T>, Vec<Vec<usize>>);
+type Creases = HashSet<(usize, usize)>;
+type Level<T> = fn(&[Vec<usize>], &[T], &Creases) -> (Vec<T>, Vec<Vec<usize>>, Creases);
+
+// Runs `level` on the positions and, separately, on the UVs. UV edges follow the creases of
+// their positions and are sharp wherever a UV seam makes them boundaries, so each chart is
+// smoothed on its own and seams stay where they are. Both spaces number their new corners the
+// same way, so the faces still line up.
+fn subdivide(
+    positions: Mesh<Vec3>,
+    uvs: Mesh<Vec2>,
+    levels: usize,
+    creases: &[(usize, usize)],
+    level: Level<Vec3>,
+    uv_level: Level<Vec2>
+) -> Option<(Mesh<Vec3>, Mesh<Vec2>)> {
+    let (mut vertices, mut faces) = positions;
+    let (mut uvs, mut uv_faces) = uvs;
+
+    if creases.iter().any(|&(a, b)| a >= vertices.len() || b >= vertices.len()) {
+        return None;
+    }
+
+    let mut creases: HashSet<_> = creases.iter().map(|&(a, b)| key(a, b)).collect();
+
+    for _ in 0..levels {
+        let mut uv_creases = HashSet::new();
+
+        for (face, uv_face) in faces.iter().zip(&uv_faces) {
+            for k in 0..face.len() {
+                let next = (k + 1) % face.len();
+
+                if creases.contains(&key(face[k], face[next])) {
+                    uv_creases.insert(key(uv_face[k], uv_face[next]));
+                }
+            }
+        }
+
+        let (new_vertices, new_faces, new_creases) = level(&faces, &vertices, &creases);
+        let (new_uvs, new_uv_faces, _) = uv_level(&uv_faces, &uvs, &uv_creases);
+
+        vertices = new_vertices;
+        faces = new_faces;
+        creases = new_creases;
+        uvs = new_uvs;
+        uv_faces = new_uv_faces;
+    }
+
+    Some(((vertices, faces), (uvs, uv_faces)))
+}
+
+fn key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+// Every edge in order of first use, with the faces on it and each vertex's edges.
+struct Edges {
+    edges: Vec<(usize, usize)>,
+    faces: Vec<Vec<usize>>,
+    index: HashMap<(usize, usize), usize>,
+    vertex_edges: Vec<Vec<usize>>
+}
+
+impl Edges {
+    fn new(faces: &[Vec<usize>], len: usize) -> Edges {
+        let mut edges = Edges {
+            edges: vec![],
+            faces: vec![],
+            index: HashMap::new(),
+            vertex_edges: vec![vec![]; len]
+        };
+
+        for (f, face) in faces.iter().enumerate() {
+            for k in 0..face.len() {
+                let edge = key(face[k], face[(k + 1) % face.len()]);
+
+                let e = match edges.index.get(&edge) {
+                    Some(&e) => e,
+                    None => {
+                        let e = edges.edges.len();
+
+                        edges.edges.push(edge);
+                        edges.faces.push(vec![]);
+                        edges.index.insert(edge, e);
+                        edges.vertex_edges[edge.0].push(e);
+                        edges.vertex_edges[edge.1].push(e);
+
+                        e
+                    }
+                };
+
+                edges.faces[e].push(f);
+            }
+        }
+
+        edges
+    }
+
+    fn get(&self, a: usize, b: usize) -> usize {
+        self.index[&key(a, b)]
+    }
+
+    // Boundaries and non-manifold edges are as sharp as creases.
+    fn is_sharp(&self, e: usize, creases: &Creases) -> bool {
+        self.faces[e].len() != 2 || creases.contains(&self.edges[e])
+    }
+
+    fn other(&self, e: usize, v: usize) -> usize {
+        let (a, b) = self.edges[e];
+
+        if a == v { b } else { a }
+    }
+
+    // Crease and corner rules shared by both schemes: vertices on exactly two sharp edges move
+    // along them, ones on more are pinned, as are the corners of a single face so that boundaries
+    // keep their ends. Others are left to the smooth rule.
+    fn sharp_vertex<T: Point>(&self, v: usize, values: &[T], creases: &Creases) -> Option<T> {
+        let sharp: Vec<_> = self.vertex_edges[v].iter().cloned().filter(|&e| self.is_sharp(e, creases)).collect();
+
+        match sharp.len() {
+            2 if self.vertex_edges[v].len() > 2 => {
+                let neighbours = values[self.other(sharp[0], v)].add(values[self.other(sharp[1], v)]);
+                Some(values[v].scale(0.75).add(neighbours.scale(0.125)))
+            }
+            n if n >= 2 || self.vertex_edges[v].is_empty() => Some(values[v]),
+            _ => None
+        }
+    }
+
+    // Sharp edges of the new mesh, where new edge points are numbered from `offset`.
+    fn split_creases(&self, creases: &Creases, offset: usize) -> Creases {
+        creases.iter().filter_map(|edge| self.index.get(edge)).flat_map(|&e| {
+            let (a, b) = self.edges[e];
+            vec![key(a, offset + e), key(offset + e, b)]
+        }).collect()
+    }
+}
+
+fn average<T: Point, I: Iterator<Item = T>>(values: I) -> T {
+    let (sum, count) = values.fold((T::zero(), 0), |(sum, count), value| (sum.add(value), count + 1));
+
+    sum.scale(1.0 / count as f32)
+}
+
+// One level, numbering the moved vertices first, then a point per edge and one per face.
+fn catmull_clark<T: Point>(
+    faces: &[Vec<usize>],
+    values: &[T],
+    creases: &Creases
+) -> (Vec<T>, Vec<Vec<usize>>, Creases) {
+    let edges = Edges::new(faces, values.len());
+    let face_points: Vec<_> = faces.iter().map(|face| average(face.iter().map(|&v| values[v]))).collect();
+
+    let edge_points: Vec<_> = (0..edges.edges.len()).map(|e| {
+        let (a, b) = edges.edges[e];
+        let midpoint = values[a].add(values[b]).scale(0.5);
+
+        if edges.is_sharp(e, creases) {
+            midpoint
+        } else {
+            let faces = &edges.faces[e];
+            midpoint.add(face_points[faces[0]].add(face_points[faces[1]]).scale(0.5)).scale(0.5)
+        }
+    }).collect();
+
+    let vertex_points: Vec<_> = (0..values.len()).map(|v| {
+        edges.sharp_vertex(v, values, creases).unwrap_or_else(|| {
+            let incident = &edges.vertex_edges[v];
+            let n = incident.len() as f32;
+            let mut adjacent: Vec<_> = incident.iter().flat_map(|&e| edges.faces[e].iter().cloned()).collect();
+
+            adjacent.sort();
+            adjacent.dedup();
+
+            let q = average(adjacent.into_iter().map(|f| face_points[f]));
+            let r = average(incident.iter().map(|&e| values[edges.edges[e].0].add(values[edges.edges[e].1]).scale(0.5)));
+
+            q.add(r.scale(2.0)).add(values[v].scale(n - 3.0)).scale(1.0 / n)
+        })
+    }).collect();
+
+    let edge_offset = values.len();
+    let face_offset = edge_offset + edges.edges.len();
+
+    let new_faces = faces.iter().enumerate().flat_map(|(f, face)| {
+        let len = face.len();
+
+        (0..len).map(|k| {
+            let (previous, current, next) = (face[(k + len - 1) % len], face[k], face[(k + 1) % len]);
+
+            vec![current, edge_offset + edges.get(current, next), face_offset + f, edge_offset + edges.get(previous, current)]
+        }).collect::<Vec<_>>()
+    }).collect();
+
+    let mut new_values = vertex_points;
+    new_values.extend(edge_points);
+    new_values.extend(face_points);
+
+    (new_values, new_faces, edges.split_creases(creases, edge_offset))
+}
+
+// One level, numbering the moved vertices first and then a point per edge.
+fn loop_level<T: Point>(
+    faces: &[Vec<usize>],
+    values: &[T],
+    creases: &Creases
+) -> (Vec<T>, Vec<Vec<usize>>, Creases) {
+    let edges = Edges::new(faces, values.len());
+
+    let edge_points: Vec<_> = (0..edges.edges.len()).map(|e| {
+        let (a, b) = edges.edges[e];
+
+        if edges.is_sharp(e, creases) {
+            values[a].add(values[b]).scale(0.5)
+        } else {
+            let opposite = |f: usize| values[faces[f].iter().cloned().find(|&v| v != a && v != b).unwrap_or(a)];
+            let sides = opposite(edges.faces[e][0]).add(opposite(edges.faces[e][1]));
+
+            values[a].add(values[b]).scale(0.375).add(sides.scale(0.125))
+        }
+    }).collect();
+
+    let vertex_points: Vec<_> = (0..values.len()).map(|v| {
+        edges.sharp_vertex(v, values, creases).unwrap_or_else(|| {
+            let incident = &edges.vertex_edges[v];
+            let n = incident.len();
+            // Warren's weights.
+            let beta = if n == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * n as f32) };
+            let neighbours = incident.iter().fold(T::zero(), |sum, &e| sum.add(values[edges.other(e, v)]));
+
+            values[v].scale(1.0 - n as f32 * beta).add(neighbours.scale(beta))
+        })
+    }).collect();
+
+    let offset = values.len();
+
+    let new_faces = faces.iter().flat_map(|face| {
+        let (a, b, c) = (face[0], face[1], face[2]);
+        let (ab, bc, ca) = (offset + edges.get(a, b), offset + edges.get(b, c), offset + edges.get(c, a));
+
+        vec![vec![a, ab, ca], vec![ab, b, bc], vec![ca, bc, c], vec![ab, bc, ca]]
+    }).collect();
+
+    let mut new_values = vertex_points;
+    new_values.extend(edge_points);
+
+    (new_values, new_faces, edges.split_creases(creases, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        math::length(math::sub(a, b)) < 1e-5
+    }
+
+    fn cube() -> PolygonMesh {
+        let vertices = (0..8).map(|i| {
+            (
+                if i & 1 != 0 { 1.0 } else { -1.0 },
+                if i & 2 != 0 { 1.0 } else { -1.0 },
+                if i & 4 != 0 { 1.0 } else { -1.0 }
+            )
+        }).collect();
+        let faces = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        let indices = faces.iter().flat_map(|face| face.iter().map(|&v| (v, 0)).collect::<Vec<_>>()).collect();
+
+        PolygonMesh::new(vertices, vec![(0.0, 0.0)], vec![4; 6], indices).unwrap()
+    }
+
+    #[test]
+    fn catmull_clark_cube() {
+        let smooth = cube().subdivide_catmull_clark(1, &[]).unwrap();
+
+        assert_eq!(smooth.vertices().len(), 8 + 12 + 6);
+        assert_eq!(smooth.face_sizes(), &[4; 24][..]);
+        assert!(close(smooth.vertices()[7], (5.0 / 9.0, 5.0 / 9.0, 5.0 / 9.0)));
+        assert!(close(smooth.vertices()[8 + 12], (0.0, 0.0, -1.0)));
+
+        let smoother = cube().subdivide_catmull_clark(2, &[]).unwrap().triangulate();
+
+        assert!(smoother.validate().is_closed());
+        assert!(smoother.volume().unwrap() < 8.0);
+    }
+
+    #[test]
+    fn catmull_clark_creases() {
+        let edges = vec![(0, 1), (2, 3), (4, 5), (6, 7), (0, 2), (1, 3), (4, 6), (5, 7), (0, 4), (1, 5), (2, 6), (3, 7)];
+        let sharp = cube().subdivide_catmull_clark(2, &edges).unwrap();
+
+        // Fully creased, the cube keeps its shape.
+        assert!(sharp.vertices().iter().all(|&(x, y, z)| (x.abs().max(y.abs()).max(z.abs()) - 1.0).abs() < 1e-5));
+        assert!((sharp.triangulate().volume().unwrap() - 8.0).abs() < 1e-4);
+
+        // A single creased edge stays on its line, where it would otherwise be pulled inwards.
+        let one = cube().subdivide_catmull_clark(1, &[(3, 7)]).unwrap();
+        let none = cube().subdivide_catmull_clark(1, &[]).unwrap();
+        let midpoint = |mesh: &PolygonMesh| mesh.vertices()[8..].iter().cloned().find(|&(x, y, z)| x > 0.5 && y > 0.5 && z.abs() < 1e-5).unwrap();
+
+        assert!(close(midpoint(&one), (1.0, 1.0, 0.0)));
+        assert!(!close(midpoint(&none), (1.0, 1.0, 0.0)));
+        assert!(cube().subdivide_catmull_clark(1, &[(0, 8)]).is_none());
+    }
+
+    #[test]
+    fn loop_sphere() {
+        let sphere = Geometry::icosphere(1.0, 0).unwrap();
+        let smooth = sphere.subdivide_loop(2, &[]).unwrap();
+
+        assert_eq!(smooth.vertices().len(), 162);
+        assert_eq!(smooth.indices().len(), 20 * 16 * 3);
+        assert!(smooth.validate().is_valid());
+        assert!(smooth.validate().is_closed());
+        assert!(smooth.vertices().iter().all(|&p| math::length(p) < 1.0 && math::length(p) > 0.7));
+
+        let lines = Geometry::with_topology(Topology::LineList, vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); 2]).unwrap();
+
+        assert!(lines.subdivide_loop(1, &[]).is_none());
+    }
+
+    #[test]
+    fn uv_seams() {
+        // Two quads side by side, UV mapped to separate charts split along x = 1.
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (2.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0), (2.0, 1.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0), (10.0, 0.0), (11.0, 0.0), (10.0, 1.0), (11.0, 1.0)],
+            vec![
+                (0, 0), (1, 1), (4, 3), (0, 0), (4, 3), (3, 2),
+                (1, 4), (2, 5), (4, 6), (2, 5), (5, 7), (4, 6)
+            ]
+        ).unwrap();
+
+        let smooth = geometry.subdivide_loop(2, &[]).unwrap();
+        let mut seam = HashMap::new();
+
+        for &(i, j) in smooth.indices() {
+            let (u, v) = smooth.uvs()[j];
+
+            if (u - 1.0).abs() < 1e-5 || (u - 10.0).abs() < 1e-5 {
+                seam.entry(i).or_insert_with(Vec::new).push((u, v));
+            }
+        }
+
+        // Both charts put every seam vertex at the same place along the seam.
+        assert!(seam.values().all(|uvs| uvs.iter().any(|uv| uv.0 > 5.0) && uvs.iter().any(|uv| uv.0 < 5.0)));
+
+        for uvs in seam.values() {
+            assert!(uvs.iter().all(|&(_, v)| (v - uvs[0].1).abs() < 1e-5));
+        }
+
+        // Without seams UVs follow the positions they are laid out on.
+        let single = Geometry::new(
+            geometry.vertices().to_vec(),
+            geometry.vertices().iter().map(|&(x, y, _)| (x, y)).collect(),
+            geometry.indices().iter().map(|&(i, _)| (i, i)).collect()
+        ).unwrap().subdivide_loop(2, &[]).unwrap();
+
+        for &(i, j) in single.indices() {
+            let (x, y, _) = single.vertices()[i];
+            assert_eq!(single.uvs()[j], (x, y));
+        }
+    }
+}
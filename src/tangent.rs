@@ -0,0 +1,691 @@
+use std::collections::HashMap;
+
+use super::geometry::Geometry;
+use super::math::{self, Vec3};
+
+struct Triangle {
+    corners: [usize; 3],
+    vertices: [usize; 3],
+    tangent: Vec3,
+    bitangent: Vec3,
+    preserves_orientation: bool,
+    groups_with_any: bool,
+    neighbors: [Option<usize>; 3],
+    groups: [Option<usize>; 3]
+}
+
+struct Group {
+    vertex: usize,
+    preserves_orientation: bool,
+    members: Vec<usize>
+}
+
+impl Triangle {
+    fn new(corners: [usize; 3], vertices: [usize; 3], positions: [Vec3; 3], uvs: [(f32, f32); 3]) -> Triangle {
+        let d1 = math::sub(positions[1], positions[0]);
+        let d2 = math::sub(positions[2], positions[0]);
+        let (s1, t1) = math::sub2(uvs[1], uvs[0]);
+        let (s2, t2) = math::sub2(uvs[2], uvs[0]);
+
+        let signed_area = s1 * t2 - t1 * s2;
+        let tangent = math::sub(math::scale(d1, t2), math::scale(d2, t1));
+        let bitangent = math::sub(math::scale(d2, s1), math::scale(d1, s2));
+
+        let mut triangle = Triangle {
+            corners,
+            vertices,
+            tangent: (0.0, 0.0, 0.0),
+            bitangent: (0.0, 0.0, 0.0),
+            preserves_orientation: signed_area > 0.0,
+            groups_with_any: true,
+            neighbors: [None; 3],
+            groups: [None; 3]
+        };
+
+        // Without UV area, or with either gradient vanishing, the triangle has no frame of its
+        // own; it joins whichever group reaches it first and adds nothing to its tangent.
+        if not_zero(signed_area) {
+            let sign = if triangle.preserves_orientation { 1.0 } else { -1.0 };
+            let (tangent_length, bitangent_length) = (math::length(tangent), math::length(bitangent));
+
+            if not_zero(tangent_length) {
+                triangle.tangent = math::scale(tangent, sign / tangent_length);
+            }
+
+            if not_zero(bitangent_length) {
+                triangle.bitangent = math::scale(bitangent, sign / bitangent_length);
+            }
+
+            triangle.groups_with_any = !not_zero(tangent_length / signed_area.abs()) ||
+                !not_zero(bitangent_length / signed_area.abs());
+        }
+
+        triangle
+    }
+
+    fn position_of(&self, vertex: usize) -> Option<usize> {
+        self.vertices.iter().position(|&v| v == vertex)
+    }
+}
+
+// MikkTSpace treats anything up to the smallest normal float as zero.
+fn not_zero(x: f32) -> bool {
+    x.abs() > f32::MIN_POSITIVE
+}
+
+fn normalize(v: Vec3) -> Vec3 {
+    if not_zero(v.0) || not_zero(v.1) || not_zero(v.2) {
+        math::scale(v, 1.0 / math::length(v))
+    } else {
+        v
+    }
+}
+
+fn project(v: Vec3, n: Vec3) -> Vec3 {
+    normalize(math::sub(v, math::scale(n, math::dot(n, v))))
+}
+
+const CELLS: usize = 2048;
+const SEED: u32 = 39_871_946;
+
+fn component(v: Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => v.0,
+        1 => v.1,
+        _ => v.2
+    }
+}
+
+// The reference's bounding box only grows its maximum when the minimum did not move.
+fn extent<I: Iterator<Item = Vec3>>(mut positions: I) -> (Vec3, Vec3) {
+    let first = positions.next().unwrap();
+
+    positions.fold((first, first), |(mut min, mut max), p| {
+        if min.0 > p.0 { min.0 = p.0 } else if max.0 < p.0 { max.0 = p.0 }
+        if min.1 > p.1 { min.1 = p.1 } else if max.1 < p.1 { max.1 = p.1 }
+        if min.2 > p.2 { min.2 = p.2 } else if max.2 < p.2 { max.2 = p.2 }
+
+        (min, max)
+    })
+}
+
+fn longest_axis(min: Vec3, max: Vec3) -> usize {
+    let (dx, dy, dz) = math::sub(max, min);
+
+    if dy > dx && dy > dz {
+        1
+    } else if dz > dx {
+        2
+    } else {
+        0
+    }
+}
+
+// Welds each corner to the first equal corner MikkTSpace finds: corners are bucketed along the
+// longest axis, then each bucket is split at its midpoint until its corners line up. The corner
+// that ends up representing a vertex decides how edges are sorted in `connect`, so this follows
+// the reference step for step.
+fn weld<F: Fn(usize, usize) -> bool>(positions: &[Vec3], same: &F) -> Vec<usize> {
+    let mut representatives: Vec<usize> = (0..positions.len()).collect();
+
+    if positions.is_empty() {
+        return representatives;
+    }
+
+    let (min, max) = extent(positions.iter().cloned());
+    let axis = longest_axis(min, max);
+    let (min, max) = (component(min, axis), component(max, axis));
+
+    let mut cells = vec![vec![]; CELLS];
+
+    for (corner, &position) in positions.iter().enumerate() {
+        let index = CELLS as f32 * ((component(position, axis) - min) / (max - min));
+        cells[(index as isize).max(0).min(CELLS as isize - 1) as usize].push(corner);
+    }
+
+    for cell in &mut cells {
+        if cell.len() >= 2 {
+            merge(cell, positions, same, &mut representatives);
+        }
+    }
+
+    representatives
+}
+
+fn merge<F: Fn(usize, usize) -> bool>(corners: &mut [usize], positions: &[Vec3], same: &F, representatives: &mut [usize]) {
+    let (min, max) = extent(corners.iter().map(|&corner| positions[corner]));
+    let axis = longest_axis(min, max);
+    let (min, max) = (component(min, axis), component(max, axis));
+    let separator = 0.5 * (max + min);
+
+    if separator >= max || separator <= min {
+        for l in 0..corners.len() {
+            if let Some(&other) = corners[..l].iter().find(|&&other| same(corners[l], other)) {
+                representatives[corners[l]] = representatives[other];
+            }
+        }
+
+        return;
+    }
+
+    let below = |corner: usize| component(positions[corner], axis) < separator;
+    let (mut l, mut r) = (0, corners.len() as isize - 1);
+
+    while l < r {
+        while l < r && below(corners[l as usize]) {
+            l += 1;
+        }
+
+        while l < r && !below(corners[r as usize]) {
+            r -= 1;
+        }
+
+        if l < r {
+            corners.swap(l as usize, r as usize);
+            l += 1;
+            r -= 1;
+        }
+    }
+
+    if l == r {
+        if below(corners[r as usize]) {
+            l += 1;
+        } else {
+            r -= 1;
+        }
+    }
+
+    if r > 0 {
+        merge(&mut corners[..=r as usize], positions, same, representatives);
+    }
+
+    if (l as usize) < corners.len() - 1 {
+        merge(&mut corners[l as usize..], positions, same, representatives);
+    }
+}
+
+// MikkTSpace's seeded quicksort over one channel of `[min vertex, max vertex, triangle]`.
+fn sort_edges(edges: &mut [[usize; 3]], channel: usize, seed: u32) {
+    if edges.len() < 2 {
+        return;
+    }
+
+    if edges.len() == 2 {
+        if edges[0][channel] > edges[1][channel] {
+            edges.swap(0, 1);
+        }
+
+        return;
+    }
+
+    let seed = seed.wrapping_add(seed.rotate_left(seed & 31)).wrapping_add(3);
+    let pivot = edges[(seed % edges.len() as u32) as usize][channel];
+    let (mut l, mut r) = (0, edges.len() as isize - 1);
+
+    while l <= r {
+        while edges[l as usize][channel] < pivot {
+            l += 1;
+        }
+
+        while edges[r as usize][channel] > pivot {
+            r -= 1;
+        }
+
+        if l <= r {
+            edges.swap(l as usize, r as usize);
+            l += 1;
+            r -= 1;
+        }
+    }
+
+    if r > 0 {
+        sort_edges(&mut edges[..=r as usize], channel, seed);
+    }
+
+    if (l as usize) < edges.len() - 1 {
+        sort_edges(&mut edges[l as usize..], channel, seed);
+    }
+}
+
+fn edge_number(vertices: [usize; 3], a: usize, b: usize) -> usize {
+    if vertices[0] == a || vertices[0] == b {
+        if vertices[1] == a || vertices[1] == b { 0 } else { 2 }
+    } else {
+        1
+    }
+}
+
+// Each edge is paired with the first unpaired opposite edge sorted after it. MikkTSpace sorts
+// runs of equal vertices by the next channel but never the last run, whose edges can then miss
+// their pair; baking tools inherit that, so it is kept.
+fn connect(triangles: &mut [Triangle]) {
+    let mut edges: Vec<_> = triangles.iter().enumerate().flat_map(|(t, triangle)| {
+        (0..3).map(move |i| {
+            let (a, b) = (triangle.vertices[i], triangle.vertices[(i + 1) % 3]);
+            [a.min(b), a.max(b), t]
+        })
+    }).collect();
+
+    sort_edges(&mut edges, 0, SEED);
+
+    for channel in 1..3 {
+        let mut start = 0;
+
+        for i in 1..edges.len() {
+            if edges[start][..channel] != edges[i][..channel] {
+                sort_edges(&mut edges[start..i], channel, SEED);
+                start = i;
+            }
+        }
+    }
+
+    for (k, edge) in edges.iter().enumerate() {
+        let (t, i) = (edge[2], edge_number(triangles[edge[2]].vertices, edge[0], edge[1]));
+
+        if triangles[t].neighbors[i].is_some() {
+            continue;
+        }
+
+        let start = triangles[t].vertices[i];
+        let opposite = edges[k + 1..].iter()
+            .take_while(|other| other[..2] == edge[..2])
+            .map(|other| (other[2], edge_number(triangles[other[2]].vertices, other[0], other[1])))
+            .find(|&(u, j)| triangles[u].neighbors[j].is_none() && triangles[u].vertices[(j + 1) % 3] == start);
+
+        if let Some((u, j)) = opposite {
+            triangles[t].neighbors[i] = Some(u);
+            triangles[u].neighbors[j] = Some(t);
+        }
+    }
+}
+
+// Groups the corners around each vertex that are connected through shared edges and agree on
+// texture orientation, visiting neighbors depth first in MikkTSpace's order.
+fn build_groups(triangles: &mut [Triangle]) -> Vec<Group> {
+    let mut groups: Vec<Group> = vec![];
+
+    for t in 0..triangles.len() {
+        for i in 0..3 {
+            if triangles[t].groups_with_any || triangles[t].groups[i].is_some() {
+                continue;
+            }
+
+            let group = groups.len();
+            let vertex = triangles[t].vertices[i];
+            let preserves_orientation = triangles[t].preserves_orientation;
+
+            groups.push(Group { vertex, preserves_orientation, members: vec![t] });
+            triangles[t].groups[i] = Some(group);
+
+            let neighbors = triangles[t].neighbors;
+            let mut stack: Vec<_> = [neighbors[(i + 2) % 3], neighbors[i]].iter().filter_map(|&n| n).collect();
+
+            while let Some(t) = stack.pop() {
+                let triangle = &mut triangles[t];
+                let i = match triangle.position_of(vertex) {
+                    Some(i) => i,
+                    None => continue
+                };
+
+                if triangle.groups[i].is_some() {
+                    continue;
+                }
+
+                if triangle.groups_with_any && triangle.groups.iter().all(Option::is_none) {
+                    triangle.preserves_orientation = preserves_orientation;
+                }
+
+                if triangle.preserves_orientation != preserves_orientation {
+                    continue;
+                }
+
+                triangle.groups[i] = Some(group);
+                groups[group].members.push(t);
+                stack.extend([triangle.neighbors[(i + 2) % 3], triangle.neighbors[i]].iter().filter_map(|&n| n));
+            }
+        }
+    }
+
+    groups
+}
+
+// Reproduces the reference MikkTSpace implementation with its default 180 degree angular
+// threshold, quirks included: corners are welded by value, triangles with coincident corners are
+// left out and copy the tangents of their vertices afterwards, and each group of corners gets the
+// angle-weighted sum of its triangles' tangents projected onto the vertex normal's plane. The
+// fixtures in `fixtures/tangents` were generated with the reference.
+impl Geometry {
+    pub fn compute_tangents(&mut self) -> Option<()> {
+        let tangents = {
+            let normals = self.normals()?;
+            let normal_indices = self.normal_indices()?;
+
+            let position = |corner: usize| self.vertices()[self.indices()[corner].0];
+            let uv = |corner: usize| self.uvs()[self.indices()[corner].1];
+            let normal = |corner: usize| normals[normal_indices[corner]];
+
+            let all = self.triangles();
+            let owners = self.corner_owners(&all);
+            let corners: Vec<_> = all.iter().flat_map(|triangle| triangle.iter().cloned()).collect();
+
+            let positions: Vec<_> = corners.iter().map(|&corner| position(corner)).collect();
+            let vertices = weld(&positions, &|a, b| {
+                let (a, b) = (corners[a], corners[b]);
+                position(a) == position(b) && normal(a) == normal(b) && uv(a) == uv(b)
+            });
+
+            let (good, degenerate): (Vec<_>, Vec<_>) = (0..all.len()).partition(|&t| {
+                let (a, b, c) = (position(all[t][0]), position(all[t][1]), position(all[t][2]));
+                a != b && a != c && b != c
+            });
+
+            let mut triangles: Vec<_> = good.iter().map(|&t| {
+                let corners = all[t];
+
+                Triangle::new(
+                    corners,
+                    [vertices[3 * t], vertices[3 * t + 1], vertices[3 * t + 2]],
+                    [position(corners[0]), position(corners[1]), position(corners[2])],
+                    [uv(corners[0]), uv(corners[1]), uv(corners[2])]
+                )
+            }).collect();
+
+            connect(&mut triangles);
+
+            let groups = build_groups(&mut triangles);
+
+            let evaluate = |members: &[usize], vertex: usize| {
+                let framed = members.iter().filter(|&&t| !triangles[t].groups_with_any);
+                let sum = framed.fold((0.0, 0.0, 0.0), |sum, &t| {
+                    let triangle = &triangles[t];
+                    let i = triangle.position_of(vertex).unwrap();
+                    let corner = triangle.corners[i];
+                    let n = normal(corner);
+
+                    let p = position(corner);
+                    let e1 = project(math::sub(position(triangle.corners[(i + 2) % 3]), p), n);
+                    let e2 = project(math::sub(position(triangle.corners[(i + 1) % 3]), p), n);
+                    let angle = f64::from(math::dot(e1, e2).clamp(-1.0, 1.0)).acos() as f32;
+
+                    math::add(sum, math::scale(project(triangle.tangent, n), angle))
+                });
+
+                normalize(sum)
+            };
+
+            // Within a group, each corner only averages the triangles whose projected tangent and
+            // bitangent are within the threshold of its own; with 180 degrees that only splits
+            // exactly opposite frames.
+            let threshold = 180f32.to_radians().cos();
+            // Corners no group reaches keep the reference's default frame.
+            let mut spaces = vec![[((1.0, 0.0, 0.0), false); 3]; triangles.len()];
+
+            for group in &groups {
+                let mut evaluated = HashMap::new();
+
+                for &t in &group.members {
+                    let triangle = &triangles[t];
+                    let i = triangle.position_of(group.vertex).unwrap();
+                    let n = normal(triangle.corners[i]);
+                    let (tangent, bitangent) = (project(triangle.tangent, n), project(triangle.bitangent, n));
+
+                    let mut members: Vec<_> = group.members.iter().cloned().filter(|&u| {
+                        let other = &triangles[u];
+
+                        triangle.groups_with_any || other.groups_with_any || u == t ||
+                            math::dot(tangent, project(other.tangent, n)) > threshold &&
+                            math::dot(bitangent, project(other.bitangent, n)) > threshold
+                    }).collect();
+
+                    members.sort();
+
+                    let tangent = *evaluated.entry(members)
+                        .or_insert_with_key(|members| evaluate(members, group.vertex));
+
+                    spaces[t][i] = (tangent, group.preserves_orientation);
+                }
+            }
+
+            let mut first = HashMap::new();
+
+            for (t, triangle) in triangles.iter().enumerate() {
+                for (i, &vertex) in triangle.vertices.iter().enumerate() {
+                    first.entry(vertex).or_insert(spaces[t][i]);
+                }
+            }
+
+            let mut results = vec![[((1.0, 0.0, 0.0), false); 3]; all.len()];
+
+            for (g, &t) in good.iter().enumerate() {
+                results[t] = spaces[g];
+            }
+
+            for &t in &degenerate {
+                for i in 0..3 {
+                    if let Some(&space) = first.get(&vertices[3 * t + i]) {
+                        results[t][i] = space;
+                    }
+                }
+            }
+
+            owners.into_iter().map(|owner| -> (f32, f32, f32, f32) {
+                match owner {
+                    Some((t, i)) => {
+                        let ((x, y, z), preserves_orientation) = results[t][i];
+                        (x, y, z, if preserves_orientation { 1.0 } else { -1.0 })
+                    }
+                    None => (0.0, 0.0, 0.0, 1.0)
+                }
+            }).collect::<Vec<_>>()
+        };
+
+        let mut values = vec![];
+        let mut unique = HashMap::new();
+
+        let indices = tangents.into_iter().map(|(x, y, z, w)| {
+            let key = (x.to_bits(), y.to_bits(), z.to_bits(), w.to_bits());
+
+            *unique.entry(key).or_insert_with(|| {
+                values.push((x, y, z, w));
+                values.len() - 1
+            })
+        }).collect();
+
+        self.set_tangents(values, indices);
+
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::normal::NormalMode;
+
+    fn quad(uvs: Vec<(f32, f32)>) -> Geometry {
+        Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)],
+            uvs,
+            vec![(0, 0), (1, 1), (2, 2), (0, 0), (2, 2), (3, 3)]
+        ).unwrap().with_normals(vec![(0.0, 0.0, 1.0)], vec![0; 6]).unwrap()
+    }
+
+    fn assert_tangents(geometry: &Geometry, expected: (f32, f32, f32, f32)) {
+        let tangents = geometry.tangents().unwrap();
+
+        for &i in geometry.tangent_indices().unwrap() {
+            let (x, y, z, w) = tangents[i];
+
+            assert!(math::length(math::sub((x, y, z), (expected.0, expected.1, expected.2))) < 1e-5);
+            assert_eq!(w, expected.3);
+        }
+    }
+
+    // Fixtures list a mesh in OBJ-like lines, then the reference tangent of each of its corners.
+    fn fixture(text: &str) -> (Geometry, Vec<(f32, f32, f32, f32)>) {
+        let mut vertices = vec![];
+        let mut uvs = vec![];
+        let mut normals = vec![];
+        let mut indices = vec![];
+        let mut normal_indices = vec![];
+        let mut tangents = vec![];
+
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            let words: Vec<_> = line.split_whitespace().collect();
+            let float = |i: usize| words[i].parse::<f32>().unwrap();
+            let index = |i: usize| words[i].parse::<usize>().unwrap();
+
+            match words[0] {
+                "v" => vertices.push((float(1), float(2), float(3))),
+                "vt" => uvs.push((float(1), float(2))),
+                "vn" => normals.push((float(1), float(2), float(3))),
+                "c" => {
+                    indices.push((index(1), index(2)));
+                    normal_indices.push(index(3));
+                }
+                "tn" => tangents.push((float(1), float(2), float(3), float(4))),
+                _ => unreachable!()
+            }
+        }
+
+        let geometry = Geometry::new(vertices, uvs, indices).unwrap().with_normals(normals, normal_indices).unwrap();
+
+        (geometry, tangents)
+    }
+
+    fn assert_fixture(text: &str) {
+        let (mut geometry, expected) = fixture(text);
+
+        geometry.compute_tangents().unwrap();
+
+        let tangents = geometry.tangents().unwrap();
+
+        for (corner, &i) in geometry.tangent_indices().unwrap().iter().enumerate() {
+            let (x, y, z, w) = tangents[i];
+            let (ex, ey, ez, ew) = expected[corner];
+
+            assert!(math::length(math::sub((x, y, z), (ex, ey, ez))) < 1e-5, "corner {}", corner);
+            assert_eq!(w, ew, "corner {}", corner);
+        }
+    }
+
+    #[test]
+    fn reference_primitives() {
+        assert_fixture(include_str!("../fixtures/tangents/uv_sphere.txt"));
+        assert_fixture(include_str!("../fixtures/tangents/torus.txt"));
+    }
+
+    #[test]
+    fn reference_mirrored() {
+        assert_fixture(include_str!("../fixtures/tangents/mirrored.txt"));
+    }
+
+    #[test]
+    fn reference_degenerate() {
+        assert_fixture(include_str!("../fixtures/tangents/degenerate.txt"));
+        assert_fixture(include_str!("../fixtures/tangents/degenerate_uvs.txt"));
+    }
+
+    #[test]
+    fn reference_unpaired_edge() {
+        assert_fixture(include_str!("../fixtures/tangents/unpaired_edge.txt"));
+    }
+
+    #[test]
+    fn requires_normals() {
+        let mut geometry = Geometry::new(vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); 3]).unwrap();
+
+        assert!(geometry.compute_tangents().is_none());
+    }
+
+    #[test]
+    fn planar() {
+        let mut geometry = quad(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+
+        geometry.compute_tangents().unwrap();
+
+        assert_eq!(geometry.tangents().unwrap().len(), 1);
+        assert_tangents(&geometry, (1.0, 0.0, 0.0, 1.0));
+
+        let mut geometry = quad(vec![(0.0, 0.0), (0.0, 1.0), (-1.0, 1.0), (-1.0, 0.0)]);
+
+        geometry.compute_tangents().unwrap();
+
+        assert_tangents(&geometry, (0.0, -1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn mirrored() {
+        let mut geometry = quad(vec![(1.0, 0.0), (0.0, 0.0), (0.0, 1.0), (1.0, 1.0)]);
+
+        geometry.compute_tangents().unwrap();
+
+        assert_tangents(&geometry, (-1.0, 0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn smooth_fold() {
+        // Two faces folded along the y axis sharing a smooth normal on the fold.
+        let s = 0.5f32.sqrt();
+        let mut geometry = Geometry::new(
+            vec![(-1.0, 0.0, 1.0), (0.0, 0.0, 0.0), (0.0, 1.0, 0.0), (-1.0, 1.0, 1.0),
+                 (1.0, 0.0, 1.0), (1.0, 1.0, 1.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (2.0, 0.0), (2.0, 1.0)],
+            vec![(0, 0), (1, 1), (2, 2), (0, 0), (2, 2), (3, 3),
+                 (1, 1), (4, 4), (5, 5), (1, 1), (5, 5), (2, 2)]
+        ).unwrap().with_normals(
+            vec![(s, 0.0, s), (0.0, 0.0, 1.0), (-s, 0.0, s)],
+            vec![0, 1, 1, 0, 1, 0, 1, 2, 2, 1, 2, 1]
+        ).unwrap();
+
+        geometry.compute_tangents().unwrap();
+
+        let tangents = geometry.tangents().unwrap();
+        let indices = geometry.tangent_indices().unwrap();
+
+        let (x, y, z, w) = tangents[indices[1]];
+
+        assert!(math::length(math::sub((x, y, z), (1.0, 0.0, 0.0))) < 1e-5);
+        assert_eq!(w, 1.0);
+        assert_eq!(indices[1], indices[6]);
+
+        let (x, y, z, _) = tangents[indices[0]];
+
+        assert!(math::length(math::sub((x, y, z), (s, 0.0, -s))) < 1e-5);
+    }
+
+    #[test]
+    fn cube() {
+        let vertices: Vec<_> = (0..8).map(|i| {
+            ((i & 1) as f32 * 2.0 - 1.0, (i >> 1 & 1) as f32 * 2.0 - 1.0, (i >> 2 & 1) as f32 * 2.0 - 1.0)
+        }).collect();
+        let quads = [[0, 4, 6, 2], [1, 3, 7, 5], [0, 1, 5, 4], [2, 6, 7, 3], [0, 2, 3, 1], [4, 5, 7, 6]];
+
+        let indices = quads.iter().flat_map(|quad| {
+            vec![(quad[0], 0), (quad[1], 1), (quad[2], 2), (quad[0], 0), (quad[2], 2), (quad[3], 3)]
+        }).collect();
+
+        let mut geometry = Geometry::new(
+            vertices.clone(),
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            indices
+        ).unwrap();
+
+        geometry.compute_normals(NormalMode::Flat);
+        geometry.compute_tangents().unwrap();
+
+        let tangents = geometry.tangents().unwrap();
+        let indices = geometry.tangent_indices().unwrap();
+
+        for (face, quad) in quads.iter().enumerate() {
+            let expected = math::normalize(math::sub(vertices[quad[1]], vertices[quad[0]]));
+
+            for corner in 6 * face..6 * face + 6 {
+                let (x, y, z, w) = tangents[indices[corner]];
+
+                assert!(math::length(math::sub((x, y, z), expected)) < 1e-5);
+                assert_eq!(w, 1.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use super::attribute::AttributeIndices;
+use super::geometry::{is_finite3, Geometry, Topology};
+use super::math::{self, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    rows: [[f32; 4]; 4]
+}
+
+impl Transform {
+    // Row-major, transforming column vectors. Only affine matrices are accepted, so the last row
+    // must be (0, 0, 0, 1).
+    pub fn new(rows: [[f32; 4]; 4]) -> Option<Transform> {
+        if !rows.iter().all(|row| row.iter().all(|value| value.is_finite())) {
+            return None;
+        }
+
+        if rows[3] != [0.0, 0.0, 0.0, 1.0] {
+            return None;
+        }
+
+        Some(Transform { rows })
+    }
+
+    pub fn identity() -> Transform {
+        Transform {
+            rows: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]]
+        }
+    }
+
+    pub fn translation((x, y, z): (f32, f32, f32)) -> Option<Transform> {
+        Transform::new([[1.0, 0.0, 0.0, x], [0.0, 1.0, 0.0, y], [0.0, 0.0, 1.0, z], [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    pub fn scale((x, y, z): (f32, f32, f32)) -> Option<Transform> {
+        Transform::new([[x, 0.0, 0.0, 0.0], [0.0, y, 0.0, 0.0], [0.0, 0.0, z, 0.0], [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    // Counter-clockwise by `angle` radians when looking down `axis`.
+    pub fn rotation(axis: (f32, f32, f32), angle: f32) -> Option<Transform> {
+        let length = math::length(axis);
+
+        if length.is_nan() || length <= 0.0 {
+            return None;
+        }
+
+        let (x, y, z) = math::normalize(axis);
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+
+        Transform::new([
+            [c + x * x * t, x * y * t - z * s, x * z * t + y * s, 0.0],
+            [y * x * t + z * s, c + y * y * t, y * z * t - x * s, 0.0],
+            [z * x * t - y * s, z * y * t + x * s, c + z * z * t, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ])
+    }
+
+    pub fn rows(&self) -> [[f32; 4]; 4] {
+        self.rows
+    }
+
+    // This transform followed by `next`.
+    pub fn then(&self, next: &Transform) -> Transform {
+        let mut rows = [[0.0; 4]; 4];
+
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| next.rows[i][k] * self.rows[k][j]).sum();
+            }
+        }
+
+        Transform { rows }
+    }
+
+    pub fn transform_point(&self, point: (f32, f32, f32)) -> (f32, f32, f32) {
+        math::add(self.transform_vector(point), (self.rows[0][3], self.rows[1][3], self.rows[2][3]))
+    }
+
+    pub fn transform_vector(&self, vector: (f32, f32, f32)) -> (f32, f32, f32) {
+        (math::dot(self.row(0), vector), math::dot(self.row(1), vector), math::dot(self.row(2), vector))
+    }
+
+    // Of the linear part, negative when the transform mirrors.
+    pub fn determinant(&self) -> f32 {
+        math::dot(self.row(0), math::cross(self.row(1), self.row(2)))
+    }
+
+    // Normals follow the inverse transpose of the linear part so they stay perpendicular to the
+    // surface under non-uniform scaling.
+    pub fn transform_normal(&self, normal: (f32, f32, f32)) -> (f32, f32, f32) {
+        let cofactors = [
+            math::cross(self.row(1), self.row(2)),
+            math::cross(self.row(2), self.row(0)),
+            math::cross(self.row(0), self.row(1))
+        ];
+        let determinant = self.determinant();
+
+        math::normalize((
+            math::dot(cofactors[0], normal) / determinant,
+            math::dot(cofactors[1], normal) / determinant,
+            math::dot(cofactors[2], normal) / determinant
+        ))
+    }
+
+    fn row(&self, i: usize) -> Vec3 {
+        (self.rows[i][0], self.rows[i][1], self.rows[i][2])
+    }
+}
+
+impl Geometry {
+    // Mirroring transforms flip every triangle's winding and tangent handedness so that faces
+    // keep pointing the same way relative to their normals; strips come back as lists then.
+    // Singular transforms, and ones that overflow, give `None`.
+    pub fn transform(&self, transform: &Transform) -> Option<Geometry> {
+        let determinant = transform.determinant();
+
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+
+        let triangles = matches!(self.topology(), Topology::TriangleList | Topology::TriangleStrip);
+
+        let mut geometry = if determinant < 0.0 && triangles {
+            let corners: Vec<_> = self.triangles().into_iter()
+                .flat_map(|triangle| vec![triangle[0], triangle[2], triangle[1]])
+                .collect();
+
+            self.with_corners(Topology::TriangleList, &corners)
+        } else {
+            self.clone()
+        };
+
+        let vertices: Vec<_> = self.vertices().iter().map(|&vertex| transform.transform_point(vertex)).collect();
+
+        if !vertices.iter().all(is_finite3) {
+            return None;
+        }
+
+        geometry.set_vertices(vertices);
+
+        let normals = geometry.normals().map(|normals| {
+            let values: Vec<_> = normals.iter().map(|&normal| transform.transform_normal(normal)).collect();
+            (values, geometry.normal_indices().unwrap().to_vec())
+        });
+
+        if let Some((values, indices)) = normals {
+            if !values.iter().all(is_finite3) {
+                return None;
+            }
+
+            geometry.set_normals(values, indices);
+        }
+
+        let tangents = geometry.tangents().map(|tangents| {
+            let values: Vec<_> = tangents.iter().map(|&(x, y, z, w)| {
+                let (x, y, z) = math::normalize(transform.transform_vector((x, y, z)));
+                (x, y, z, if determinant < 0.0 { -w } else { w })
+            }).collect();
+
+            (values, geometry.tangent_indices().unwrap().to_vec())
+        });
+
+        if let Some((values, indices)) = tangents {
+            if !values.iter().all(|&(x, y, z, _)| is_finite3(&(x, y, z))) {
+                return None;
+            }
+
+            geometry.set_tangents(values, indices);
+        }
+
+        Some(geometry)
+    }
+
+    // Concatenates geometries of the same kind of primitive into one list. Every part needs the
+    // same channels and attributes, matched by name. Attributes indexed by position or UV in every
+    // part stay that way, the others get their own indices.
+    pub fn merge(geometries: &[&Geometry]) -> Option<Geometry> {
+        let parts: Vec<_> = geometries.iter().map(|geometry| {
+            geometry.with_corners(geometry.topology().to_list(), &geometry.list_corners())
+        }).collect();
+        let first = parts.first()?;
+
+        for part in &parts {
+            if part.topology() != first.topology() {
+                return None;
+            }
+
+            if part.normals().is_some() != first.normals().is_some() ||
+                part.tangents().is_some() != first.tangents().is_some() {
+                return None;
+            }
+
+            if part.attributes().len() != first.attributes().len() ||
+                first.attributes().iter().any(|channel| part.attribute(channel.attribute().name()).is_none()) {
+                return None;
+            }
+        }
+
+        let mut vertices = vec![];
+        let mut uvs = vec![];
+        let mut indices = vec![];
+        let mut normals = (vec![], vec![]);
+        let mut tangents = (vec![], vec![]);
+
+        for part in &parts {
+            let (vertex_offset, uv_offset) = (vertices.len(), uvs.len());
+
+            vertices.extend_from_slice(part.vertices());
+            uvs.extend_from_slice(part.uvs());
+            indices.extend(part.indices().iter().map(|&(i, j)| (i + vertex_offset, j + uv_offset)));
+
+            if let Some(values) = part.normals() {
+                let offset = normals.0.len();
+
+                normals.0.extend_from_slice(values);
+                normals.1.extend(part.normal_indices().unwrap().iter().map(|&i| i + offset));
+            }
+
+            if let Some(values) = part.tangents() {
+                let offset = tangents.0.len();
+
+                tangents.0.extend_from_slice(values);
+                tangents.1.extend(part.tangent_indices().unwrap().iter().map(|&i| i + offset));
+            }
+        }
+
+        let mut geometry = Geometry::from_parts(first.topology(), vertices, uvs, indices);
+
+        if first.normals().is_some() {
+            geometry.set_normals(normals.0, normals.1);
+        }
+
+        if first.tangents().is_some() {
+            geometry.set_tangents(tangents.0, tangents.1);
+        }
+
+        for channel in first.attributes() {
+            let name = channel.attribute().name();
+            let channels: Vec<_> = parts.iter().map(|part| part.attribute(name).unwrap()).collect();
+
+            let mut attribute = channel.attribute().clone();
+
+            for other in &channels[1..] {
+                attribute = attribute.concat(other.attribute())?;
+            }
+
+            let shared = match *channel.indices() {
+                AttributeIndices::Own(_) => false,
+                ref indices => channels.iter().all(|other| other.indices() == indices)
+            };
+
+            let indices = if shared {
+                channel.indices().clone()
+            } else {
+                let mut offset = 0;
+                let mut own = vec![];
+
+                for (part, other) in parts.iter().zip(&channels) {
+                    own.extend(part.indices().iter().enumerate().map(|(corner, &index)| other.index(corner, index) + offset));
+                    offset += other.attribute().len();
+                }
+
+                AttributeIndices::Own(own)
+            };
+
+            geometry = geometry.with_attribute(attribute, indices)?;
+        }
+
+        Some(geometry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use super::super::attribute::{Attribute, AttributeValues};
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        math::length(math::sub(a, b)) < 1e-5
+    }
+
+    fn triangle() -> Geometry {
+        Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            vec![(0, 0), (1, 1), (2, 2)]
+        ).unwrap().with_normals(
+            vec![(0.0, 0.0, 1.0)],
+            vec![0; 3]
+        ).unwrap().with_tangents(
+            vec![(1.0, 0.0, 0.0, 1.0)],
+            vec![0; 3]
+        ).unwrap()
+    }
+
+    fn face_normal(geometry: &Geometry) -> Vec3 {
+        let position = |corner: usize| geometry.vertices()[geometry.indices()[corner].0];
+        math::normalize(math::cross(math::sub(position(1), position(0)), math::sub(position(2), position(0))))
+    }
+
+    #[test]
+    fn rotate_translate() {
+        let transform = Transform::rotation((0.0, 1.0, 0.0), PI / 2.0).unwrap()
+            .then(&Transform::translation((0.0, 0.0, 5.0)).unwrap());
+        let geometry = triangle().transform(&transform).unwrap();
+
+        assert!(close(geometry.vertices()[1], (0.0, 0.0, 4.0)));
+        assert!(close(geometry.normals().unwrap()[0], (1.0, 0.0, 0.0)));
+        assert!(close(face_normal(&geometry), (1.0, 0.0, 0.0)));
+        assert_eq!(geometry.tangents().unwrap()[0].3, 1.0);
+        assert!(close(geometry.bounds().unwrap().aabb().max(), (0.0, 1.0, 5.0)));
+    }
+
+    #[test]
+    fn mirror() {
+        let geometry = triangle().transform(&Transform::scale((1.0, 1.0, -1.0)).unwrap()).unwrap();
+        let (x, y, z, w) = geometry.tangents().unwrap()[0];
+
+        assert_eq!(geometry.indices(), &[(0, 0), (2, 2), (1, 1)]);
+        assert!(close(geometry.normals().unwrap()[0], (0.0, 0.0, -1.0)));
+        assert!(close(face_normal(&geometry), (0.0, 0.0, -1.0)));
+        assert!(close((x, y, z), (1.0, 0.0, 0.0)));
+        assert_eq!(w, -1.0);
+    }
+
+    #[test]
+    fn non_uniform_scale() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (-1.0, 1.0, 0.0), (0.0, 0.0, 1.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0)]
+        ).unwrap().with_normals(
+            vec![math::normalize((1.0, 1.0, 0.0))],
+            vec![0; 3]
+        ).unwrap();
+
+        let scaled = geometry.transform(&Transform::scale((2.0, 1.0, 1.0)).unwrap()).unwrap();
+
+        assert!(close(scaled.normals().unwrap()[0], face_normal(&scaled)));
+        assert!(Transform::new([[1.0, 0.0, 0.0, 0.0]; 4]).is_none());
+        assert!(geometry.transform(&Transform::scale((0.0, 1.0, 1.0)).unwrap()).is_none());
+    }
+
+    #[test]
+    fn merge() {
+        let a = triangle().with_attribute(
+            Attribute::new("weight", 1, AttributeValues::F32(vec![0.0, 1.0, 2.0])).unwrap(),
+            AttributeIndices::Vertices
+        ).unwrap();
+        let b = triangle().transform(&Transform::translation((0.0, 0.0, 2.0)).unwrap()).unwrap().with_attribute(
+            Attribute::new("weight", 1, AttributeValues::F32(vec![5.0])).unwrap(),
+            AttributeIndices::Own(vec![0; 3])
+        ).unwrap();
+
+        let merged = Geometry::merge(&[&a, &b, &a]).unwrap();
+
+        assert_eq!(merged.vertices().len(), 9);
+        assert_eq!(merged.indices()[4], (4, 4));
+        assert_eq!(merged.normal_indices().unwrap(), &[0, 0, 0, 1, 1, 1, 2, 2, 2]);
+        assert!(close(merged.bounds().unwrap().aabb().max(), (1.0, 1.0, 2.0)));
+
+        let weight = merged.attribute("weight").unwrap();
+
+        assert_eq!(weight.indices(), &AttributeIndices::Own(vec![0, 1, 2, 3, 3, 3, 4, 5, 6]));
+        assert_eq!(weight.attribute().values(), &AttributeValues::F32(vec![0.0, 1.0, 2.0, 5.0, 0.0, 1.0, 2.0]));
+
+        let shared = Geometry::merge(&[&a, &a]).unwrap();
+
+        assert_eq!(shared.attribute("weight").unwrap().indices(), &AttributeIndices::Vertices);
+        assert!(Geometry::merge(&[&a, &triangle()]).is_none());
+        assert!(Geometry::merge(&[]).is_none());
+    }
+
+    #[test]
+    fn merge_strips() {
+        let strip = Geometry::with_topology(
+            Topology::TriangleStrip,
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0)],
+            vec![(0.0, 0.0)],
+            vec![(0, 0), (1, 0), (2, 0), (3, 0)]
+        ).unwrap();
+        let list = Geometry::new(vec![(5.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); 3]).unwrap();
+        let lines = Geometry::with_topology(Topology::LineList, vec![(0.0, 0.0, 0.0)], vec![(0.0, 0.0)], vec![(0, 0); 2]).unwrap();
+
+        let merged = Geometry::merge(&[&strip, &list]).unwrap();
+
+        assert_eq!(merged.topology(), Topology::TriangleList);
+        assert_eq!(merged.indices().iter().map(|&(i, _)| i).collect::<Vec<_>>(), vec![0, 1, 2, 2, 1, 3, 4, 4, 4]);
+        assert!(Geometry::merge(&[&strip, &lines]).is_none());
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use super::geometry::Geometry;
+use super::math;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationReport {
+    degenerate_triangles: Vec<[usize; 3]>,
+    duplicate_triangles: Vec<[usize; 3]>,
+    non_manifold_edges: Vec<(usize, usize)>,
+    inconsistent_edges: Vec<(usize, usize)>,
+    isolated_vertices: Vec<usize>,
+    zero_area_uv_triangles: Vec<[usize; 3]>,
+    boundary_edges: Vec<(usize, usize)>
+}
+
+impl ValidationReport {
+    // Triangles, as corners, with zero area or a position used twice.
+    pub fn degenerate_triangles(&self) -> &[[usize; 3]] {
+        &self.degenerate_triangles[..]
+    }
+
+    // Triangles over the same positions as an earlier one, whatever their winding.
+    pub fn duplicate_triangles(&self) -> &[[usize; 3]] {
+        &self.duplicate_triangles[..]
+    }
+
+    // Edges, as pairs of positions, shared by more than two triangles.
+    pub fn non_manifold_edges(&self) -> &[(usize, usize)] {
+        &self.non_manifold_edges[..]
+    }
+
+    // Edges whose two triangles run along them in the same direction.
+    pub fn inconsistent_edges(&self) -> &[(usize, usize)] {
+        &self.inconsistent_edges[..]
+    }
+
+    // Positions no corner uses.
+    pub fn isolated_vertices(&self) -> &[usize] {
+        &self.isolated_vertices[..]
+    }
+
+    // Triangles that are not degenerate but whose UVs are, which breaks tangent generation.
+    pub fn zero_area_uv_triangles(&self) -> &[[usize; 3]] {
+        &self.zero_area_uv_triangles[..]
+    }
+
+    // Edges used by a single triangle.
+    pub fn boundary_edges(&self) -> &[(usize, usize)] {
+        &self.boundary_edges[..]
+    }
+
+    // Open boundaries are allowed, every other issue is not.
+    pub fn is_valid(&self) -> bool {
+        self.degenerate_triangles.is_empty() &&
+            self.duplicate_triangles.is_empty() &&
+            self.non_manifold_edges.is_empty() &&
+            self.inconsistent_edges.is_empty() &&
+            self.isolated_vertices.is_empty() &&
+            self.zero_area_uv_triangles.is_empty()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.boundary_edges.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RepairStats {
+    removed_degenerate: usize,
+    removed_duplicates: usize,
+    flipped_triangles: usize,
+    removed_vertices: usize
+}
+
+impl RepairStats {
+    pub fn removed_degenerate(&self) -> usize {
+        self.removed_degenerate
+    }
+
+    pub fn removed_duplicates(&self) -> usize {
+        self.removed_duplicates
+    }
+
+    pub fn flipped_triangles(&self) -> usize {
+        self.flipped_triangles
+    }
+
+    pub fn removed_vertices(&self) -> usize {
+        self.removed_vertices
+    }
+}
+
+impl Geometry {
+    pub fn validate(&self) -> ValidationReport {
+        let triangles = self.triangles();
+        let (degenerate, triangles): (Vec<_>, Vec<_>) = triangles.into_iter().partition(|&triangle| self.is_degenerate(triangle));
+
+        let mut used = vec![false; self.vertices().len()];
+
+        for &(i, _) in self.indices() {
+            used[i] = true;
+        }
+
+        let mut non_manifold_edges = vec![];
+        let mut inconsistent_edges = vec![];
+        let mut boundary_edges = vec![];
+
+        for (edge, sides) in self.edges(&triangles) {
+            match sides.len() {
+                1 => boundary_edges.push(edge),
+                2 if sides[0].1 == sides[1].1 => inconsistent_edges.push(edge),
+                2 => {}
+                _ => non_manifold_edges.push(edge)
+            }
+        }
+
+        ValidationReport {
+            degenerate_triangles: degenerate,
+            duplicate_triangles: self.duplicates(&triangles),
+            non_manifold_edges,
+            inconsistent_edges,
+            isolated_vertices: (0..used.len()).filter(|&i| !used[i]).collect(),
+            zero_area_uv_triangles: triangles.iter().cloned().filter(|triangle| {
+                let uv = |k: usize| self.uvs()[self.indices()[triangle[k]].1];
+                math::cross2(math::sub2(uv(1), uv(0)), math::sub2(uv(2), uv(0))) == 0.0
+            }).collect(),
+            boundary_edges
+        }
+    }
+
+    // Removes degenerate and duplicate triangles and unused values, and flips triangles to agree
+    // with the majority of their connected patch. Non-manifold edges, zero-area UVs and open
+    // boundaries are left for the caller. Strips come back as lists, other topologies only lose
+    // their unused values.
+    pub fn repair(&self) -> (Geometry, RepairStats) {
+        let triangles = self.triangles();
+        let total = triangles.len();
+
+        if total == 0 {
+            let mut geometry = self.clone();
+            geometry.remove_unused_values();
+
+            let stats = RepairStats {
+                removed_degenerate: 0,
+                removed_duplicates: 0,
+                flipped_triangles: 0,
+                removed_vertices: self.vertices().len() - geometry.vertices().len()
+            };
+
+            return (geometry, stats);
+        }
+
+        let triangles: Vec<_> = triangles.into_iter().filter(|&triangle| !self.is_degenerate(triangle)).collect();
+        let removed_degenerate = total - triangles.len();
+
+        let duplicates: HashSet<_> = self.duplicates(&triangles).into_iter().collect();
+        let mut triangles: Vec<_> = triangles.into_iter().filter(|triangle| !duplicates.contains(triangle)).collect();
+
+        let flipped = self.orient(&triangles);
+
+        for &t in &flipped {
+            triangles[t].swap(1, 2);
+        }
+
+        let geometry = self.with_triangles(&triangles);
+
+        let stats = RepairStats {
+            removed_degenerate,
+            removed_duplicates: duplicates.len(),
+            flipped_triangles: flipped.len(),
+            removed_vertices: self.vertices().len() - geometry.vertices().len()
+        };
+
+        (geometry, stats)
+    }
+
+    fn position(&self, corner: usize) -> usize {
+        self.indices()[corner].0
+    }
+
+    fn is_degenerate(&self, triangle: [usize; 3]) -> bool {
+        let (a, b, c) = (self.position(triangle[0]), self.position(triangle[1]), self.position(triangle[2]));
+
+        if a == b || b == c || c == a {
+            return true;
+        }
+
+        let (a, b, c) = (self.vertices()[a], self.vertices()[b], self.vertices()[c]);
+
+        math::length(math::cross(math::sub(b, a), math::sub(c, a))) == 0.0
+    }
+
+    fn duplicates(&self, triangles: &[[usize; 3]]) -> Vec<[usize; 3]> {
+        let mut seen = HashSet::new();
+
+        triangles.iter().cloned().filter(|triangle| {
+            let mut key = [self.position(triangle[0]), self.position(triangle[1]), self.position(triangle[2])];
+            key.sort();
+
+            !seen.insert(key)
+        }).collect()
+    }
+
+    // Maps each edge, lower position first, to its triangles and whether they run along it from
+    // the lower position.
+    fn edges(&self, triangles: &[[usize; 3]]) -> BTreeMap<(usize, usize), Vec<(usize, bool)>> {
+        let mut edges = BTreeMap::new();
+
+        for (t, triangle) in triangles.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (self.position(triangle[k]), self.position(triangle[(k + 1) % 3]));
+
+                edges.entry((a.min(b), a.max(b))).or_insert_with(Vec::new).push((t, a < b));
+            }
+        }
+
+        edges
+    }
+
+    // Walks each patch of triangles joined by manifold edges and returns the ones to flip so that
+    // neighbours agree. Patches that cannot agree, like a Möbius strip, keep a best effort.
+    fn orient(&self, triangles: &[[usize; 3]]) -> Vec<usize> {
+        let mut neighbours = vec![vec![]; triangles.len()];
+
+        for (_, sides) in self.edges(triangles) {
+            if sides.len() == 2 {
+                let ((a, a_forward), (b, b_forward)) = (sides[0], sides[1]);
+                let same = a_forward == b_forward;
+
+                neighbours[a].push((b, same));
+                neighbours[b].push((a, same));
+            }
+        }
+
+        let mut flip = vec![None; triangles.len()];
+        let mut flipped = vec![];
+
+        for start in 0..triangles.len() {
+            if flip[start].is_some() {
+                continue;
+            }
+
+            let mut patch = vec![start];
+            let mut queue = VecDeque::new();
+
+            flip[start] = Some(false);
+            queue.push_back(start);
+
+            while let Some(t) = queue.pop_front() {
+                for &(n, same) in &neighbours[t] {
+                    if flip[n].is_none() {
+                        flip[n] = Some(flip[t].unwrap() != same);
+                        patch.push(n);
+                        queue.push_back(n);
+                    }
+                }
+            }
+
+            let count = patch.iter().filter(|&&t| flip[t] == Some(true)).count();
+            let majority = count * 2 > patch.len();
+
+            flipped.extend(patch.into_iter().filter(|&t| flip[t] != Some(majority)));
+        }
+
+        flipped.sort();
+        flipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::attribute::{Attribute, AttributeIndices, AttributeValues};
+    use super::super::geometry::Topology;
+
+    fn grid() -> Vec<(f32, f32, f32)> {
+        (0..9).map(|i| ((i % 3) as f32, (i / 3) as f32, 0.0)).collect()
+    }
+
+    fn uvs() -> Vec<(f32, f32)> {
+        (0..9).map(|i| ((i % 3) as f32 * 0.5, (i / 3) as f32 * 0.5)).collect()
+    }
+
+    #[test]
+    fn clean() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            vec![
+                (0, 0), (2, 2), (1, 1),
+                (0, 0), (1, 1), (3, 2),
+                (1, 0), (2, 1), (3, 2),
+                (2, 0), (0, 1), (3, 2)
+            ]
+        ).unwrap();
+
+        let report = geometry.validate();
+
+        assert!(report.is_valid());
+        assert!(report.is_closed());
+        assert_eq!(geometry.repair().1, RepairStats {
+            removed_degenerate: 0,
+            removed_duplicates: 0,
+            flipped_triangles: 0,
+            removed_vertices: 0
+        });
+    }
+
+    #[test]
+    fn issues() {
+        let mut vertices = grid();
+        vertices.push((5.0, 5.0, 5.0));
+
+        let geometry = Geometry::new(
+            vertices,
+            uvs(),
+            vec![
+                (0, 0), (1, 1), (4, 4),
+                (0, 0), (4, 4), (3, 3),
+                // Flipped.
+                (1, 1), (4, 4), (2, 2),
+                (2, 2), (5, 5), (4, 4),
+                // Duplicate of the first, the other way round.
+                (0, 0), (4, 4), (1, 1),
+                // Degenerate, then collinear.
+                (3, 3), (3, 3), (4, 4),
+                (3, 3), (4, 4), (5, 5),
+                // Zero UV area.
+                (3, 0), (4, 0), (7, 0)
+            ]
+        ).unwrap();
+
+        let report = geometry.validate();
+
+        assert_eq!(report.degenerate_triangles(), &[[15, 16, 17], [18, 19, 20]]);
+        assert_eq!(report.duplicate_triangles(), &[[12, 13, 14]]);
+        // The duplicate shares its edges with two other triangles.
+        assert_eq!(report.non_manifold_edges(), &[(0, 4), (1, 4)]);
+        assert_eq!(report.inconsistent_edges(), &[(2, 4)]);
+        assert_eq!(report.isolated_vertices(), &[6, 8, 9]);
+        assert_eq!(report.zero_area_uv_triangles(), &[[21, 22, 23]]);
+        assert!(!report.is_valid());
+        assert!(!report.is_closed());
+
+        let (repaired, stats) = geometry.repair();
+        let report = repaired.validate();
+
+        assert_eq!(stats.removed_degenerate(), 2);
+        assert_eq!(stats.removed_duplicates(), 1);
+        assert_eq!(stats.flipped_triangles(), 1);
+        assert_eq!(stats.removed_vertices(), 3);
+        assert_eq!(repaired.indices().len(), 15);
+        assert!(report.inconsistent_edges().is_empty());
+        assert!(report.isolated_vertices().is_empty());
+        assert_eq!(report.zero_area_uv_triangles().len(), 1);
+    }
+
+    #[test]
+    fn non_manifold() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, -1.0, 0.0), (0.0, 0.0, 1.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            vec![(0, 0), (1, 1), (2, 2), (1, 0), (0, 1), (3, 2), (0, 0), (1, 1), (4, 2)]
+        ).unwrap();
+
+        let report = geometry.validate();
+
+        assert_eq!(report.non_manifold_edges(), &[(0, 1)]);
+        assert!(report.inconsistent_edges().is_empty());
+        assert_eq!(geometry.repair().1.flipped_triangles(), 0);
+    }
+
+    #[test]
+    fn repair_keeps_channels() {
+        let geometry = Geometry::with_topology(
+            Topology::TriangleStrip,
+            grid(),
+            uvs(),
+            vec![(0, 0), (3, 3), (1, 1), (1, 1), (4, 4), (2, 2)]
+        ).unwrap().with_normals(
+            vec![(0.0, 0.0, 1.0), (0.0, 0.0, -1.0)],
+            vec![0; 6]
+        ).unwrap().with_attribute(
+            Attribute::new("weight", 1, AttributeValues::F32((0..9).map(|i| i as f32).collect())).unwrap(),
+            AttributeIndices::Vertices
+        ).unwrap();
+
+        let report = geometry.validate();
+
+        // Strips stitch with degenerate triangles on purpose, so those are not reported.
+        assert!(report.degenerate_triangles().is_empty());
+        assert_eq!(report.isolated_vertices(), &[5, 6, 7, 8]);
+
+        let (repaired, stats) = geometry.repair();
+
+        assert_eq!(repaired.topology(), Topology::TriangleList);
+        assert_eq!(stats.removed_vertices(), 4);
+        assert_eq!(repaired.vertices(), &[(0.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (2.0, 0.0, 0.0)]);
+        assert_eq!(repaired.normals().unwrap(), &[(0.0, 0.0, 1.0)]);
+        assert_eq!(
+            repaired.attribute("weight").unwrap().attribute().values(),
+            &AttributeValues::F32(vec![0.0, 3.0, 1.0, 4.0, 2.0])
+        );
+        assert_eq!(repaired.bounds().unwrap().aabb().max(), (2.0, 1.0, 0.0));
+        assert!(repaired.validate().is_valid());
+    }
+}
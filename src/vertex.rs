@@ -0,0 +1,443 @@
+use super::attribute::{Attribute, AttributeValues};
+use super::geometry::{Geometry, GeometryExpanded};
+use super::indexed::{IndexBuffer, IndexedMesh};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VertexFormat {
+    F32,
+    F16,
+    Snorm16,
+    Unorm16,
+    Snorm8,
+    Unorm8,
+    U16,
+    U8
+}
+
+impl VertexFormat {
+    pub fn size(&self) -> usize {
+        match *self {
+            VertexFormat::F32 => 4,
+            VertexFormat::F16 | VertexFormat::Snorm16 | VertexFormat::Unorm16 | VertexFormat::U16 => 2,
+            VertexFormat::Snorm8 | VertexFormat::Unorm8 | VertexFormat::U8 => 1
+        }
+    }
+
+    fn write(&self, value: f32, bytes: &mut Vec<u8>) {
+        let quantize = |value: f32, min: f32, max: f32, scale: f32| {
+            (value.max(min).min(max) * scale).round() as i32
+        };
+
+        match *self {
+            VertexFormat::F32 => write_u32(value.to_bits(), bytes),
+            VertexFormat::F16 => write_u16(f16_bits(value), bytes),
+            VertexFormat::Snorm16 => write_u16(quantize(value, -1.0, 1.0, 32767.0) as i16 as u16, bytes),
+            VertexFormat::Unorm16 => write_u16(quantize(value, 0.0, 1.0, 65535.0) as u16, bytes),
+            VertexFormat::Snorm8 => bytes.push(quantize(value, -1.0, 1.0, 127.0) as i8 as u8),
+            VertexFormat::Unorm8 => bytes.push(quantize(value, 0.0, 1.0, 255.0) as u8),
+            VertexFormat::U16 => write_u16(quantize(value, 0.0, 65535.0, 1.0) as u16, bytes),
+            VertexFormat::U8 => bytes.push(quantize(value, 0.0, 255.0, 1.0) as u8)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VertexSource {
+    Position,
+    Uv,
+    Normal,
+    Tangent,
+    Attribute(String)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexElement {
+    source: VertexSource,
+    format: VertexFormat,
+    components: usize,
+    offset: usize
+}
+
+impl VertexElement {
+    pub fn source(&self) -> &VertexSource {
+        &self.source
+    }
+
+    pub fn format(&self) -> VertexFormat {
+        self.format
+    }
+
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexLayout {
+    elements: Vec<VertexElement>,
+    alignment: usize,
+    stride: usize,
+    end: usize
+}
+
+impl VertexLayout {
+    pub fn new(alignment: usize) -> Option<VertexLayout> {
+        if !alignment.is_power_of_two() {
+            return None;
+        }
+
+        Some(
+            VertexLayout {
+                elements: vec![],
+                alignment,
+                stride: 0,
+                end: 0
+            }
+        )
+    }
+
+    // Elements start at the next multiple of the alignment and the stride grows to fit them.
+    pub fn with_element(
+        mut self,
+        source: VertexSource,
+        format: VertexFormat,
+        components: usize
+    ) -> Option<VertexLayout> {
+        if !(1..=4).contains(&components) {
+            return None;
+        }
+
+        let offset = align(self.end, self.alignment);
+
+        self.end = offset + components * format.size();
+        self.stride = self.stride.max(align(self.end, self.alignment));
+        self.elements.push(VertexElement { source, format, components, offset });
+
+        Some(self)
+    }
+
+    pub fn with_stride(self, stride: usize) -> Option<VertexLayout> {
+        if stride < self.end || !stride.is_multiple_of(self.alignment) {
+            return None;
+        }
+
+        Some(
+            VertexLayout {
+                stride,
+                ..self
+            }
+        )
+    }
+
+    pub fn elements(&self) -> &[VertexElement] {
+        &self.elements[..]
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VertexBuffer {
+    layout: VertexLayout,
+    data: Vec<u8>,
+    len: usize,
+    indices: Option<IndexBuffer>
+}
+
+impl VertexBuffer {
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[..]
+    }
+
+    pub fn len_vertices(&self) -> usize {
+        self.len
+    }
+
+    pub fn indices(&self) -> Option<&IndexBuffer> {
+        self.indices.as_ref()
+    }
+}
+
+struct Sources<'a> {
+    vertices: &'a [(f32, f32, f32)],
+    uvs: &'a [(f32, f32)],
+    normals: Option<&'a [(f32, f32, f32)]>,
+    tangents: Option<&'a [(f32, f32, f32, f32)]>,
+    attributes: &'a [Attribute]
+}
+
+impl<'a> Sources<'a> {
+    // Integer attributes keep their raw values, which the integer formats write unchanged.
+    fn values(&self, source: &VertexSource) -> Option<(usize, Vec<f32>)> {
+        match *source {
+            VertexSource::Position => {
+                Some((3, self.vertices.iter().flat_map(|&(x, y, z)| vec![x, y, z]).collect()))
+            }
+            VertexSource::Uv => {
+                Some((2, self.uvs.iter().flat_map(|&(u, v)| vec![u, v]).collect()))
+            }
+            VertexSource::Normal => {
+                self.normals.map(|normals| {
+                    (3, normals.iter().flat_map(|&(x, y, z)| vec![x, y, z]).collect())
+                })
+            }
+            VertexSource::Tangent => {
+                self.tangents.map(|tangents| {
+                    (4, tangents.iter().flat_map(|&(x, y, z, w)| vec![x, y, z, w]).collect())
+                })
+            }
+            VertexSource::Attribute(ref name) => {
+                self.attributes.iter().find(|attribute| attribute.name() == name).map(|attribute| {
+                    let values = match *attribute.values() {
+                        AttributeValues::F32(ref values) => values.clone(),
+                        AttributeValues::U16(ref values) => values.iter().map(|&x| x as f32).collect(),
+                        AttributeValues::U8(ref values) => values.iter().map(|&x| x as f32).collect()
+                    };
+
+                    (attribute.components(), values)
+                })
+            }
+        }
+    }
+
+    fn interleave(&self, layout: &VertexLayout) -> Option<Vec<u8>> {
+        let len = self.vertices.len();
+        let mut columns = vec![];
+
+        for element in &layout.elements {
+            let (components, values) = self.values(&element.source)?;
+
+            if components != element.components {
+                return None;
+            }
+
+            columns.push(values);
+        }
+
+        let mut data = Vec::with_capacity(len * layout.stride);
+
+        for vertex in 0..len {
+            let start = data.len();
+
+            for (element, values) in layout.elements.iter().zip(columns.iter()) {
+                let components = element.components;
+
+                data.resize(start + element.offset, 0);
+
+                for &value in &values[vertex * components..(vertex + 1) * components] {
+                    element.format.write(value, &mut data);
+                }
+            }
+
+            data.resize(start + layout.stride, 0);
+        }
+
+        Some(data)
+    }
+}
+
+impl IndexedMesh {
+    pub fn to_vertex_buffer(&self, layout: &VertexLayout) -> Option<VertexBuffer> {
+        let sources = Sources {
+            vertices: self.vertices(),
+            uvs: self.uvs(),
+            normals: self.normals(),
+            tangents: self.tangents(),
+            attributes: self.attributes()
+        };
+
+        Some(
+            VertexBuffer {
+                layout: layout.clone(),
+                data: sources.interleave(layout)?,
+                len: self.len_vertices(),
+                indices: Some(self.indices().clone())
+            }
+        )
+    }
+}
+
+impl Geometry {
+    pub fn to_vertex_buffer(&self, layout: &VertexLayout) -> Option<VertexBuffer> {
+        self.to_indexed_mesh().to_vertex_buffer(layout)
+    }
+}
+
+impl GeometryExpanded {
+    pub fn to_vertex_buffer(&self, layout: &VertexLayout) -> Option<VertexBuffer> {
+        let sources = Sources {
+            vertices: self.vertices(),
+            uvs: self.uvs(),
+            normals: self.normals(),
+            tangents: self.tangents(),
+            attributes: self.attributes()
+        };
+
+        Some(
+            VertexBuffer {
+                layout: layout.clone(),
+                data: sources.interleave(layout)?,
+                len: self.vertices().len(),
+                indices: None
+            }
+        )
+    }
+}
+
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+fn write_u16(value: u16, bytes: &mut Vec<u8>) {
+    bytes.push(value as u8);
+    bytes.push((value >> 8) as u8);
+}
+
+fn write_u32(value: u32, bytes: &mut Vec<u8>) {
+    write_u16(value as u16, bytes);
+    write_u16((value >> 16) as u16, bytes);
+}
+
+// Rounds to nearest even, flushing to signed zero below the smallest subnormal and saturating
+// to infinity above the largest finite half.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    let (half, shift, mantissa) = if exponent > 0 {
+        ((exponent as u32) << 10 | mantissa >> 13, 13, mantissa)
+    } else if exponent >= -10 {
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+
+        (mantissa >> shift, shift, mantissa)
+    } else {
+        return sign;
+    };
+
+    let remainder = mantissa & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let round = remainder > halfway || (remainder == halfway && half & 1 == 1);
+
+    sign | (half + round as u32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::attribute::AttributeIndices;
+
+    fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+        bytes[offset] as u16 | (bytes[offset + 1] as u16) << 8
+    }
+
+    fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_bits(read_u16(bytes, offset) as u32 | (read_u16(bytes, offset + 2) as u32) << 16)
+    }
+
+    #[test]
+    fn f16() {
+        assert_eq!(f16_bits(1.0), 0x3c00);
+        assert_eq!(f16_bits(-2.0), 0xc000);
+        assert_eq!(f16_bits(0.1), 0x2e66);
+        assert_eq!(f16_bits(65504.0), 0x7bff);
+        assert_eq!(f16_bits(1e6), 0x7c00);
+        assert_eq!(f16_bits(2.0f32.powi(-24)), 0x0001);
+        assert_eq!(f16_bits(2.0f32.powi(-26)), 0x0000);
+        assert_eq!(f16_bits(-0.0), 0x8000);
+    }
+
+    #[test]
+    fn layout() {
+        let layout = VertexLayout::new(4).unwrap()
+            .with_element(VertexSource::Position, VertexFormat::F32, 3).unwrap()
+            .with_element(VertexSource::Normal, VertexFormat::Snorm8, 3).unwrap()
+            .with_element(VertexSource::Uv, VertexFormat::F16, 2).unwrap();
+
+        let offsets: Vec<_> = layout.elements().iter().map(|element| element.offset()).collect();
+
+        assert_eq!(offsets, vec![0, 12, 16]);
+        assert_eq!(layout.stride(), 20);
+        assert!(layout.clone().with_stride(18).is_none());
+        assert!(layout.clone().with_stride(22).is_none());
+        assert_eq!(layout.with_stride(32).unwrap().stride(), 32);
+        assert!(VertexLayout::new(3).is_none());
+    }
+
+    #[test]
+    fn interleave() {
+        let geometry = Geometry::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            vec![(0, 0), (1, 1), (2, 2)]
+        ).unwrap().with_normals(
+            vec![(0.0, 0.0, -1.0)],
+            vec![0; 3]
+        ).unwrap().with_attribute(
+            Attribute::new("color", 4, AttributeValues::U8(vec![255, 128, 0, 255])).unwrap(),
+            AttributeIndices::Own(vec![0; 3])
+        ).unwrap();
+
+        let layout = VertexLayout::new(4).unwrap()
+            .with_element(VertexSource::Position, VertexFormat::F32, 3).unwrap()
+            .with_element(VertexSource::Normal, VertexFormat::Snorm16, 3).unwrap()
+            .with_element(VertexSource::Attribute("color".to_owned()), VertexFormat::U8, 4).unwrap()
+            .with_element(VertexSource::Uv, VertexFormat::Unorm16, 2).unwrap();
+
+        let buffer = geometry.to_vertex_buffer(&layout).unwrap();
+        let data = buffer.data();
+
+        assert_eq!(layout.stride(), 28);
+        assert_eq!(buffer.len_vertices(), 3);
+        assert_eq!(data.len(), 3 * 28);
+        assert_eq!(buffer.indices(), Some(&IndexBuffer::U16(vec![0, 1, 2])));
+
+        assert_eq!(read_f32(data, 28), 1.0);
+        assert_eq!(read_u16(data, 28 + 16), (-32767i16) as u16);
+        assert_eq!(read_u16(data, 28 + 18), 0);
+        assert_eq!(&data[28 + 20..28 + 24], &[255, 128, 0, 255]);
+        assert_eq!(read_u16(data, 28 + 24), 65535);
+        assert_eq!(read_u16(data, 28 + 26), 0);
+
+        let expanded = geometry.expand().to_vertex_buffer(&layout).unwrap();
+
+        assert_eq!(expanded.data(), data);
+        assert!(expanded.indices().is_none());
+
+        let tangents = VertexLayout::new(1).unwrap()
+            .with_element(VertexSource::Tangent, VertexFormat::F32, 4).unwrap();
+
+        assert!(geometry.to_vertex_buffer(&tangents).is_none());
+
+        let padded = VertexLayout::new(1).unwrap()
+            .with_element(VertexSource::Position, VertexFormat::F32, 4).unwrap();
+
+        assert!(geometry.to_vertex_buffer(&padded).is_none());
+    }
+}
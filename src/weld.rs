@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+
+use super::attribute::AttributeIndices;
+use super::geometry::{Geometry, GeometryExpanded};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeldOptions {
+    position_epsilon: f32,
+    uv_epsilon: f32,
+    preserve_seams: bool,
+    preserve_normals: bool
+}
+
+impl WeldOptions {
+    pub fn new() -> WeldOptions {
+        WeldOptions {
+            position_epsilon: 0.0,
+            uv_epsilon: 0.0,
+            preserve_seams: false,
+            preserve_normals: false
+        }
+    }
+
+    pub fn with_position_epsilon(self, epsilon: f32) -> Option<WeldOptions> {
+        if !epsilon.is_finite() || epsilon < 0.0 {
+            return None;
+        }
+
+        Some(
+            WeldOptions {
+                position_epsilon: epsilon,
+                ..self
+            }
+        )
+    }
+
+    pub fn with_uv_epsilon(self, epsilon: f32) -> Option<WeldOptions> {
+        if !epsilon.is_finite() || epsilon < 0.0 {
+            return None;
+        }
+
+        Some(
+            WeldOptions {
+                uv_epsilon: epsilon,
+                ..self
+            }
+        )
+    }
+
+    // Positions are only merged when their corners' UVs also weld, so UV seams stay open.
+    pub fn with_preserved_seams(self, preserve_seams: bool) -> WeldOptions {
+        WeldOptions {
+            preserve_seams,
+            ..self
+        }
+    }
+
+    // Positions are only merged when their corners' normals are identical, so hard edges stay
+    // split.
+    pub fn with_preserved_normals(self, preserve_normals: bool) -> WeldOptions {
+        WeldOptions {
+            preserve_normals,
+            ..self
+        }
+    }
+
+    pub fn position_epsilon(&self) -> f32 {
+        self.position_epsilon
+    }
+
+    pub fn uv_epsilon(&self) -> f32 {
+        self.uv_epsilon
+    }
+
+    pub fn preserves_seams(&self) -> bool {
+        self.preserve_seams
+    }
+
+    pub fn preserves_normals(&self) -> bool {
+        self.preserve_normals
+    }
+}
+
+impl Default for WeldOptions {
+    fn default() -> WeldOptions {
+        WeldOptions::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeldStats {
+    vertices_before: usize,
+    vertices_after: usize,
+    uvs_before: usize,
+    uvs_after: usize
+}
+
+impl WeldStats {
+    pub fn vertices_before(&self) -> usize {
+        self.vertices_before
+    }
+
+    pub fn vertices_after(&self) -> usize {
+        self.vertices_after
+    }
+
+    pub fn merged_vertices(&self) -> usize {
+        self.vertices_before - self.vertices_after
+    }
+
+    pub fn uvs_before(&self) -> usize {
+        self.uvs_before
+    }
+
+    pub fn uvs_after(&self) -> usize {
+        self.uvs_after
+    }
+
+    pub fn merged_uvs(&self) -> usize {
+        self.uvs_before - self.uvs_after
+    }
+}
+
+impl GeometryExpanded {
+    pub fn weld(&self, options: &WeldOptions) -> (Geometry, WeldStats) {
+        let positions: Vec<_> = self.vertices().iter().map(|&(x, y, z)| vec![x, y, z]).collect();
+        let uvs: Vec<_> = self.uvs().iter().map(|&(u, v)| vec![u, v]).collect();
+
+        let (uv_firsts, uv_indices) = cluster(&uvs, options.uv_epsilon, |_, _| true);
+        let (firsts, indices) = cluster(&positions, options.position_epsilon, |a, b| {
+            let seam = !options.preserve_seams || uv_indices[a] == uv_indices[b];
+            let normal = !options.preserve_normals ||
+                self.normals().is_none_or(|normals| normals[a] == normals[b]);
+
+            seam && normal
+        });
+
+        let stats = WeldStats {
+            vertices_before: positions.len(),
+            vertices_after: firsts.len(),
+            uvs_before: uvs.len(),
+            uvs_after: uv_firsts.len()
+        };
+
+        let mut geometry = Geometry::from_parts(
+            self.topology(),
+            firsts.iter().map(|&i| self.vertices()[i]).collect(),
+            uv_firsts.iter().map(|&i| self.uvs()[i]).collect(),
+            indices.into_iter().zip(uv_indices).collect()
+        );
+
+        if let Some(normals) = self.normals() {
+            let points: Vec<_> = normals.iter().map(|&(x, y, z)| vec![x, y, z]).collect();
+            let (firsts, indices) = cluster(&points, 0.0, |_, _| true);
+
+            geometry.set_normals(firsts.iter().map(|&i| normals[i]).collect(), indices);
+        }
+
+        if let Some(tangents) = self.tangents() {
+            let points: Vec<_> = tangents.iter().map(|&(x, y, z, w)| vec![x, y, z, w]).collect();
+            let (firsts, indices) = cluster(&points, 0.0, |_, _| true);
+
+            geometry.set_tangents(firsts.iter().map(|&i| tangents[i]).collect(), indices);
+        }
+
+        for attribute in self.attributes() {
+            let (attribute, indices) = attribute.condense();
+
+            geometry = geometry.with_attribute(attribute, AttributeIndices::Own(indices)).unwrap();
+        }
+
+        (geometry, stats)
+    }
+}
+
+// Greedily assigns each point to the first earlier representative within `epsilon` that
+// `compatible` accepts, looking candidates up in a grid of `epsilon`-sized cells. A zero epsilon
+// merges equal points only. Returns the representatives and every point's cluster.
+fn cluster<F: Fn(usize, usize) -> bool>(
+    points: &[Vec<f32>],
+    epsilon: f32,
+    compatible: F
+) -> (Vec<usize>, Vec<usize>) {
+    let mut firsts = vec![];
+    let mut cells: HashMap<Vec<i64>, Vec<usize>> = HashMap::new();
+
+    let indices = points.iter().enumerate().map(|(i, point)| {
+        // Non-finite points never compare equal, and would not fit in a cell anyway.
+        if !point.iter().all(|x| x.is_finite()) {
+            firsts.push(i);
+            return firsts.len() - 1;
+        }
+
+        let cell: Vec<i64> = point.iter().map(|&x| {
+            if epsilon > 0.0 {
+                (x / epsilon).floor().clamp(-1e18, 1e18) as i64
+            } else {
+                (x + 0.0).to_bits() as i64
+            }
+        }).collect();
+
+        let neighbors = if epsilon > 0.0 { 3usize.pow(cell.len() as u32) } else { 1 };
+
+        let found = (0..neighbors).filter_map(|neighbor| {
+            let key: Vec<i64> = cell.iter().enumerate().map(|(axis, &c)| {
+                if epsilon > 0.0 {
+                    c + (neighbor / 3usize.pow(axis as u32) % 3) as i64 - 1
+                } else {
+                    c
+                }
+            }).collect();
+
+            cells.get(&key)
+        }).flat_map(|members| members.iter().cloned()).filter(|&cluster| {
+            let first = firsts[cluster];
+
+            if epsilon > 0.0 {
+                distance(&points[first], point) <= epsilon && compatible(first, i)
+            } else {
+                same(&points[first], point) && compatible(first, i)
+            }
+        }).min();
+
+        match found {
+            Some(cluster) => cluster,
+            None => {
+                firsts.push(i);
+                cells.entry(cell).or_default().push(firsts.len() - 1);
+                firsts.len() - 1
+            }
+        }
+    }).collect();
+
+    (firsts, indices)
+}
+
+fn same(a: &[f32], b: &[f32]) -> bool {
+    a.iter().zip(b.iter()).all(|(x, y)| x == y)
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded(offset: f32) -> GeometryExpanded {
+        GeometryExpanded::new(
+            vec![(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0),
+                 (1.0 + offset, 0.0, 0.0), (1.0, 1.0, 0.0), (offset, 1.0, 0.0)],
+            vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0 + offset, 0.0), (1.0, 1.0), (0.5, 1.0)]
+        ).unwrap()
+    }
+
+    #[test]
+    fn options_invalid() {
+        assert!(WeldOptions::new().with_position_epsilon(-1.0).is_none());
+        assert!(WeldOptions::new().with_uv_epsilon(f32::NAN).is_none());
+        assert_eq!(WeldOptions::new().with_uv_epsilon(0.5).unwrap().uv_epsilon(), 0.5);
+        assert_eq!(WeldOptions::default(), WeldOptions::new());
+    }
+
+    #[test]
+    fn epsilon() {
+        let (geometry, stats) = expanded(1e-4).weld(&WeldOptions::new());
+
+        assert_eq!(stats.merged_vertices(), 0);
+        assert_eq!(geometry.vertices().len(), 6);
+
+        let options = WeldOptions::new()
+            .with_position_epsilon(1e-3).unwrap()
+            .with_uv_epsilon(1e-3).unwrap();
+        let (geometry, stats) = expanded(1e-4).weld(&options);
+
+        assert_eq!(stats.vertices_before(), 6);
+        assert_eq!(stats.vertices_after(), 4);
+        assert_eq!(stats.merged_uvs(), 1);
+        assert_eq!(geometry.vertices()[..4], [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (1.0, 1.0, 0.0)]);
+        assert_eq!(geometry.indices()[3], (1, 1));
+        assert_eq!(geometry.indices()[5], (2, 4));
+    }
+
+    #[test]
+    fn preserve_seams() {
+        let options = WeldOptions::new()
+            .with_position_epsilon(1e-3).unwrap()
+            .with_preserved_seams(true);
+        let (geometry, stats) = expanded(0.0).weld(&options);
+
+        assert_eq!(stats.vertices_after(), 5);
+        assert_eq!(geometry.indices()[3].0, 1);
+        assert_ne!(geometry.indices()[5].0, 2);
+    }
+
+    #[test]
+    fn preserve_normals() {
+        let expanded = expanded(0.0).with_normals(
+            vec![(0.0, 0.0, 1.0), (0.0, 0.0, 1.0), (0.0, 0.0, 1.0),
+                 (0.0, 0.0, -1.0), (0.0, 0.0, 1.0), (0.0, 0.0, 1.0)]
+        ).unwrap();
+
+        let (geometry, stats) = expanded.weld(&WeldOptions::new());
+
+        assert_eq!(stats.vertices_after(), 4);
+        assert_eq!(geometry.normals().unwrap(), &[(0.0, 0.0, 1.0), (0.0, 0.0, -1.0)]);
+
+        let (geometry, stats) = expanded.weld(&WeldOptions::new().with_preserved_normals(true));
+
+        assert_eq!(stats.vertices_after(), 5);
+        assert_eq!(geometry.indices()[3].0, 3);
+        assert_eq!(geometry.normal_indices().unwrap(), &[0, 0, 0, 1, 0, 0]);
+    }
+}
@@ -1,236 +1,392 @@
 use std::any::{Any, TypeId};
+use std::borrow::Cow;
 use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::rc::Rc;
-use std::str;
 
 use bincode;
 
-use super::block::{Block, BlockInner};
-use super::geometry::GeometryData;
-use super::object::Object;
-
-macro_rules! match_block {
-    ( $slf:ident, $rc:expr, $ptr:expr, $index:expr, [ $typ:ty ] ) => {
-        match $rc.downcast::<$typ>() {
-            Ok(rc) => {
-                match Rc::try_unwrap(rc) {
-                    Ok(tie) => {
-                        // TODO: fix when NLLs
-                        let block = Some(tie.into_block($slf));
-
-                        if $index < $slf.blocks.len() {
-                            $slf.blocks[$index] = block;
-                        } else {
-                            $slf.blocks.push_back(block);
-                        }
-
-                        if $slf.indices.contains_key($ptr) {
-                            $slf.indices.remove($ptr).unwrap();
-                        }
-                    }
-                    Err(rc) => {
-                        if !$slf.indices.contains_key($ptr) {
-                            $slf.blocks.push_back(None);
-                            $slf.indices.insert(*$ptr, $index);
-                            $slf.rcs.insert($index, rc);
-                        }
-                    }
-                };
-            }
-            Err(_) => unreachable!()
-        };
-    };
-    ( $slf:ident, $rc:expr, $ptr:expr, $index:expr, [ $typ:ty, $( $typs:ty ),* ] ) => {
-        match $rc.downcast::<$typ>() {
-            Ok(rc) => {
-                match Rc::try_unwrap(rc) {
-                    Ok(tie) => {
-                        // TODO: fix when NLLs
-                        let block = Some(tie.into_block($slf));
-
-                        if $index < $slf.blocks.len() {
-                            $slf.blocks[$index] = block;
-                        } else {
-                            $slf.blocks.push_back(block);
-                        }
-
-                        if $slf.indices.contains_key($ptr) {
-                            $slf.indices.remove($ptr).unwrap();
-                        }
-                    }
-                    Err(rc) => {
-                        if !$slf.indices.contains_key($ptr) {
-                            $slf.blocks.push_back(None);
-                            $slf.indices.insert(*$ptr, $index);
-                            $slf.rcs.insert($index, rc);
-                        }
-                    }
-                };
-            }
-            Err(rc) => match_block!($slf, rc, $ptr, $index, [ $( $typs ),* ])
-        };
-    };
+use super::block::{Block, BlockKind};
+use super::lazy::Lazy;
+use super::legacy;
+use super::metadata::Metadata;
+
+const MAGIC: &[u8] = b"yarn";
+// Before versions, the magic was followed by the number of blocks, which never gets this large.
+const VERSIONED: u64 = u64::MAX;
+// 1: metadata, then every block as its own byte string, then the roots.
+const VERSION: u32 = 1;
+
+#[derive(Debug)]
+enum Slot {
+    Decoded(Box<Block>),
+    Encoded(Vec<u8>)
+}
+
+impl Slot {
+    fn kind(&self) -> Option<BlockKind> {
+        match *self {
+            Slot::Decoded(ref block) => Some(block.kind()),
+            Slot::Encoded(ref bytes) => BlockKind::peek(bytes)
+        }
+    }
+
+    fn decode(self) -> Option<Block> {
+        match self {
+            Slot::Decoded(block) => Some(*block),
+            Slot::Encoded(bytes) => bincode::deserialize(&bytes).ok()
+        }
+    }
+}
+
+type IntoBlock = fn(Rc<dyn Any>, &mut Yarn) -> Result<Block, Rc<dyn Any>>;
+
+fn into_block<T: Tie>(rc: Rc<dyn Any>, yarn: &mut Yarn) -> Result<Block, Rc<dyn Any>> {
+    match Rc::try_unwrap(rc.downcast::<T>().ok().unwrap()) {
+        Ok(tie) => Ok(tie.into_block(yarn)),
+        Err(rc) => Err(rc)
+    }
+}
+
+struct Tied {
+    index: usize,
+    rc: Rc<dyn Any>,
+    into_block: IntoBlock
+}
+
+impl fmt::Debug for Tied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tied")
+            .field("index", &self.index)
+            .field("strong_count", &Rc::strong_count(&self.rc))
+            .finish()
+    }
 }
 
 #[derive(Debug)]
 pub struct Yarn {
-    blocks: VecDeque<Option<Block>>,
-    rcs: HashMap<usize, Rc<Any>>,
-    indices: HashMap<*const (), usize>,
-    allocated: Vec<usize>
+    metadata: Option<Metadata>,
+    blocks: Vec<Option<Slot>>,
+    empty: usize,
+    free: Vec<usize>,
+    roots: VecDeque<usize>,
+    tied: HashMap<*const (), Tied>,
+    tied_encoded: HashMap<*const (), (usize, Box<dyn Any>)>,
+    lazies: HashMap<usize, Box<dyn Any>>
 }
 
 impl Yarn {
     pub fn new() -> Yarn {
         Yarn {
-            blocks: VecDeque::new(),
-            rcs: HashMap::new(),
-            indices: HashMap::new(),
-            allocated: vec![]
+            metadata: None,
+            blocks: vec![],
+            empty: 0,
+            free: vec![],
+            roots: VecDeque::new(),
+            tied: HashMap::new(),
+            tied_encoded: HashMap::new(),
+            lazies: HashMap::new()
         }
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Option<Yarn> {
-        let slice = &bytes[0..4];
+        let mut reader = Yarn::versioned(bytes)?;
+
+        let (metadata, blocks, roots) = match reader {
+            Some(ref mut reader) => {
+                let metadata = bincode::deserialize_from(reader, bincode::Infinite).ok()?;
+                let blocks: Vec<Vec<u8>> = bincode::deserialize_from(reader, bincode::Infinite).ok()?;
+                let roots: VecDeque<usize> = bincode::deserialize_from(reader, bincode::Infinite).ok()?;
+
+                if roots.iter().any(|&index| index >= blocks.len() || blocks[index].is_empty()) {
+                    return None;
+                }
 
-        if let Ok(string) = str::from_utf8(slice) {
-            if string != "yarn" {
-                return None;
+                let blocks: Vec<_> = blocks.into_iter().map(|bytes| {
+                    if bytes.is_empty() {
+                        None
+                    } else {
+                        Some(Slot::Encoded(bytes))
+                    }
+                }).collect();
+
+                (metadata, blocks, roots)
             }
-        } else {
+            None => {
+                let (blocks, roots) = legacy::read(&bytes[4..])?;
+
+                (None, blocks.into_iter().map(|block| Some(Slot::Decoded(Box::new(block)))).collect(), roots)
+            }
+        };
+
+        let free = (0..blocks.len()).filter(|&index| blocks[index].is_none()).collect();
+
+        Some(
+            Yarn {
+                metadata,
+                blocks,
+                empty: 0,
+                free,
+                roots,
+                tied: HashMap::new(),
+                tied_encoded: HashMap::new(),
+                lazies: HashMap::new()
+            }
+        )
+    }
+
+    pub fn metadata_from_bytes(bytes: &[u8]) -> Option<Metadata> {
+        let mut reader = Yarn::versioned(bytes)??;
+
+        bincode::deserialize_from(&mut reader, bincode::Infinite).ok()?
+    }
+
+    // Checks the magic and version, and returns what follows them, or `None` for a yarn from
+    // before versions, which starts its blocks right after the magic instead.
+    fn versioned(bytes: &[u8]) -> Option<Option<&[u8]>> {
+        if bytes.len() < 4 || &bytes[0..4] != MAGIC {
             return None;
         }
 
-        if let Ok(blocks) = bincode::deserialize::<Vec<Block>>(&bytes[4..]) {
-            Some(
-                Yarn {
-                    blocks: blocks.into_iter().map(|block| Some(block)).collect(),
-                    rcs: HashMap::new(),
-                    indices: HashMap::new(),
-                    allocated: vec![]
+        let mut reader = &bytes[4..];
+
+        match bincode::deserialize_from::<_, u64, _>(&mut reader, bincode::Infinite) {
+            Ok(VERSIONED) => {
+                match bincode::deserialize_from::<_, u32, _>(&mut reader, bincode::Infinite).ok()? {
+                    VERSION => Some(Some(reader)),
+                    _ => None
                 }
-            )
-        } else {
-            None
+            }
+            _ => Some(None)
         }
     }
 
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = Some(metadata);
+    }
+
+    pub fn take_metadata(&mut self) -> Option<Metadata> {
+        self.metadata.take()
+    }
+
     pub fn len_blocks(&self) -> usize {
         self.blocks.len()
     }
 
+    pub fn len_roots(&self) -> usize {
+        self.roots.len()
+    }
+
     pub fn is_entangled(&self) -> bool {
-        self.blocks.iter().any(|option| option.is_none())
+        self.empty > 0
     }
 
-    pub fn into_bytes(self) -> Result<Vec<u8>, Yarn> {
+    pub fn into_bytes(mut self) -> Result<Vec<u8>, Yarn> {
+        self.tie_orphans();
+
         if self.is_entangled() {
             return Err(self);
         }
 
-        let mut bytes: Vec<_> = "yarn".as_bytes().into_iter().map(|byte| *byte).collect();
-
-        let blocks: Vec<_> = self.blocks.into_iter().map(|option| option.unwrap()).collect();
+        let mut bytes = MAGIC.to_vec();
+
+        match (
+            bincode::serialize(&VERSIONED, bincode::Infinite),
+            bincode::serialize(&VERSION, bincode::Infinite),
+            bincode::serialize(&self.metadata, bincode::Infinite)
+        ) {
+            (Ok(versioned), Ok(version), Ok(metadata)) => {
+                bytes.extend(versioned);
+                bytes.extend(version);
+                bytes.extend(metadata);
+            }
+            _ => return Err(self)
+        }
 
-        if let Ok(blocks) = bincode::serialize(&blocks, bincode::Infinite) {
-            bytes.extend(blocks.into_iter());
-            Ok(bytes)
-        } else {
-            Err(
-                Yarn {
-                    blocks: blocks.into_iter().map(|block| Some(block)).collect(),
-                    rcs: HashMap::new(),
-                    indices: HashMap::new(),
-                    allocated: vec![]
+        // Every block is its own byte string, so that `from_bytes` can peek at their kinds and
+        // leave them encoded until a `Lazy` holding one is read.
+        // TODO: fix when NLLs
+        let blocks = {
+            let blocks: Option<Vec<Cow<[u8]>>> = self.blocks.iter().map(|option| {
+                match *option {
+                    Some(Slot::Decoded(ref block)) => {
+                        bincode::serialize(block, bincode::Infinite).ok().map(Cow::Owned)
+                    }
+                    Some(Slot::Encoded(ref bytes)) => Some(Cow::Borrowed(&bytes[..])),
+                    // Without entanglement only freed root slots are empty, and no block refers
+                    // to them.
+                    None => Some(Cow::Borrowed(&[][..]))
                 }
-            )
+            }).collect();
+
+            blocks.and_then(|blocks| bincode::serialize(&blocks, bincode::Infinite).ok())
+        };
+
+        match (blocks, bincode::serialize(&self.roots, bincode::Infinite)) {
+            (Some(blocks), Ok(roots)) => {
+                bytes.extend(blocks);
+                bytes.extend(roots);
+                Ok(bytes)
+            }
+            _ => Err(self)
         }
     }
 
     pub fn tied_type_id(&self) -> Option<TypeId> {
-        let block = self.blocks.iter().find(|option| option.is_some())?;
+        let index = *self.roots.front()?;
+        let slot = self.blocks[index].as_ref()?;
 
-        match *block {
-            Some(Block(BlockInner::GeometryData(_))) => Some(TypeId::of::<GeometryData>()),
-            Some(Block(BlockInner::Object(_))) => Some(TypeId::of::<Object>()),
-            None => None
+        slot.kind().map(|kind| kind.type_id())
+    }
+
+    fn reserve_block(&mut self) -> usize {
+        self.empty += 1;
+
+        match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.blocks.push(None);
+                self.blocks.len() - 1
+            }
         }
     }
 
-    pub(super) fn allocate_block(&mut self) {
-        // TODO: fix when NLLs
-        let index = self.blocks.len();
-        self.allocated.push(index);
-        self.blocks.push_back(None);
+    fn fill_block(&mut self, index: usize, block: Block) {
+        self.blocks[index] = Some(Slot::Decoded(Box::new(block)));
+        self.empty -= 1;
     }
 
-    pub(super) fn tie_block(&mut self, block: Block) -> usize {
-        if self.allocated.is_empty() {
-            let index = self.blocks.len();
+    fn take_block(&mut self, index: usize) -> Option<Slot> {
+        let slot = self.blocks.get_mut(index)?.take()?;
+        self.empty += 1;
 
-            self.blocks.push_back(Some(block));
+        Some(slot)
+    }
 
-            index
-        } else {
-            let index = self.allocated.pop().unwrap();
+    fn tie_root<T: Tie>(&mut self, tie: T) -> usize {
+        let index = self.reserve_block();
 
-            self.blocks[index] = Some(block);
+        // TODO: fix when NLLs
+        let block = tie.into_block(self);
+        self.fill_block(index, block);
+        self.roots.push_back(index);
 
-            index
-        }
+        index
     }
 
-    pub(super) fn untie_block(&mut self) -> Option<Block> {
-        let option = self.blocks.iter_mut().find(|option| option.is_some())?;
-        Some(option.take().unwrap())
+    fn untie_root(&mut self) -> Option<Block> {
+        let index = self.roots.pop_front()?;
+        let slot = self.take_block(index)?;
+
+        // Roots are never referenced by other blocks, so their slots can be handed out again.
+        self.empty -= 1;
+        self.free.push(index);
+
+        slot.decode()
     }
 
-    pub(super) fn tie_rc(&mut self, mut rc: Rc<Any>) -> usize {
-        let ptr = &*rc as *const Any as *const ();
+    pub(super) fn tie_rc<T: Tie>(&mut self, rc: Rc<T>) -> usize {
+        let ptr = &*rc as *const T as *const ();
 
-        // TODO: fix when NLLs
-        let index = if self.indices.contains_key(&ptr) {
-            let index = *self.indices.get(&ptr).unwrap();
-            rc = self.rcs.remove(&index).unwrap();
-            index
-        } else {
-            self.blocks.len()
+        // Tracked entries own a strong reference, so `ptr` cannot be freed and handed to
+        // another value while it is still a key.
+        let (index, rc): (_, Rc<dyn Any>) = match self.tied.remove(&ptr) {
+            Some(tied) => {
+                drop(rc);
+                (tied.index, tied.rc)
+            }
+            None => (self.reserve_block(), rc)
         };
 
-        match_block!(self, rc, &ptr, index, [
-            GeometryData,
-            Object
-        ]);
+        self.tie_tracked(ptr, index, rc, into_block::<T>);
 
         index
     }
 
-    pub(super) fn untie_rc(&mut self, index: usize) -> Option<Rc<Any>> {
-        // TODO: fix when NLLs
-        if self.rcs.contains_key(&index) {
-            self.rcs.get(&index).map(|rc| rc.clone())
-        } else {
-            if index < self.blocks.len() {
-                // TODO: fix when NLLs
-                let block = self.blocks.get_mut(index).unwrap().take().unwrap();
-
-                let rc: Rc<Any> = match block.0 {
-                    BlockInner::GeometryData(_) => {
-                        Rc::new(GeometryData::from_block(block, self).unwrap())
-                    }
-                    BlockInner::Object(_) => {
-                        Rc::new(Object::from_block(block, self).unwrap())
-                    }
-                };
+    // Lazies that were never read go back as the bytes they were untied from. Clones share them,
+    // so they are tracked like `Rc`s and the entry keeps them alive while its address is a key.
+    pub(super) fn tie_lazy<T: Leaf>(&mut self, lazy: Lazy<T>) -> usize {
+        if let Some(rc) = lazy.loaded() {
+            // Dropped first so that it does not hold on to another strong reference.
+            drop(lazy);
+            return self.tie_rc(rc);
+        }
+
+        let ptr = lazy.ptr();
+
+        if let Some(&(index, _)) = self.tied_encoded.get(&ptr) {
+            return index;
+        }
 
-                self.rcs.insert(index, rc.clone());
+        let index = self.reserve_block();
 
-                Some(rc)
-            } else {
-                None
+        self.blocks[index] = Some(Slot::Encoded(lazy.bytes().unwrap_or(&[]).to_vec()));
+        self.empty -= 1;
+        self.tied_encoded.insert(ptr, (index, Box::new(lazy)));
+
+        index
+    }
+
+    fn tie_tracked(&mut self, ptr: *const (), index: usize, rc: Rc<dyn Any>, into_block: IntoBlock) {
+        match into_block(rc, self) {
+            Ok(block) => self.fill_block(index, block),
+            Err(rc) => {
+                self.tied.insert(ptr, Tied { index, rc, into_block });
             }
         }
     }
+
+    // Ties the values whose only remaining reference is held by the yarn itself, i.e. the
+    // ones whose other owners were dropped instead of tied.
+    fn tie_orphans(&mut self) {
+        loop {
+            let orphans: Vec<_> = self.tied.iter()
+                .filter(|&(_, tied)| Rc::strong_count(&tied.rc) == 1)
+                .map(|(ptr, _)| *ptr)
+                .collect();
+
+            if orphans.is_empty() {
+                break;
+            }
+
+            for ptr in orphans {
+                let Tied { index, rc, into_block } = self.tied.remove(&ptr).unwrap();
+                self.tie_tracked(ptr, index, rc, into_block);
+            }
+        }
+    }
+
+    pub(super) fn untie_lazy<T: Leaf>(&mut self, index: usize) -> Option<Lazy<T>> {
+        if let Some(lazy) = self.lazies.get(&index) {
+            return lazy.downcast_ref::<Lazy<T>>().cloned();
+        }
+
+        // TODO: fix when NLLs
+        let kind = self.blocks.get(index)?.as_ref()?.kind()?;
+
+        if kind.type_id() != TypeId::of::<T>() {
+            return None;
+        }
+
+        let lazy = match self.take_block(index).unwrap() {
+            Slot::Encoded(bytes) => Lazy::encoded(bytes),
+            Slot::Decoded(block) => Lazy::new(Rc::new(T::from_block(*block, self)?))
+        };
+
+        self.lazies.insert(index, Box::new(lazy.clone()));
+
+        Some(lazy)
+    }
+}
+
+impl Default for Yarn {
+    fn default() -> Yarn {
+        Yarn::new()
+    }
 }
 
 pub trait Tie: Sized + 'static {
@@ -238,9 +394,7 @@ pub trait Tie: Sized + 'static {
     fn from_block(block: Block, yarn: &mut Yarn) -> Option<Self>;
 
     fn tie(self, yarn: &mut Yarn) -> usize {
-        // TODO: fix when NLLs
-        let block = self.into_block(yarn);
-        yarn.tie_block(block)
+        yarn.tie_root(self)
     }
 
     fn untie(yarn: &mut Yarn) -> Option<Self> {
@@ -249,7 +403,7 @@ pub trait Tie: Sized + 'static {
         }
 
         // TODO: fix when NLLs
-        let block = yarn.untie_block();
+        let block = yarn.untie_root();
         match block {
             Some(block) => Tie::from_block(block, yarn),
             _ => None
@@ -257,12 +411,17 @@ pub trait Tie: Sized + 'static {
     }
 }
 
+pub trait Leaf: Tie {}
+
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+    use std::ptr;
     use std::rc::Rc;
 
     use super::*;
-    use super::super::geometry::Geometry;
+    use super::super::geometry::{Geometry, GeometryData};
+    use super::super::metadata::MetadataValue;
     use super::super::object::Object;
     use super::super::yarn::{Tie, Yarn};
 
@@ -273,7 +432,7 @@ mod tests {
                 Geometry::new(
                     vec![(1.0, 2.0, 3.0); 10],
                     vec![(0.0, 1.0); 6],
-                    vec![(1, 2); 16]
+                    vec![(1, 2); 18]
                 ).unwrap()
             )
         );
@@ -290,13 +449,13 @@ mod tests {
         let object1 = Object::untie(&mut yarn).unwrap();
         let object2 = Object::untie(&mut yarn).unwrap();
 
-        match *object1.geometry() {
+        match *object1.geometry().unwrap() {
             GeometryData::Geometry(ref geometry) => {
                 assert_eq!(geometry.vertices(), &[(1.0, 2.0, 3.0); 10]);
             }
             _ => unreachable!()
         }
-        match *object2.geometry() {
+        match *object2.geometry().unwrap() {
             GeometryData::Geometry(ref geometry) => {
                 assert_eq!(geometry.vertices(), &[(1.0, 2.0, 3.0); 10]);
             }
@@ -304,8 +463,200 @@ mod tests {
         }
 
         assert_eq!(
-            object1.geometry() as *const GeometryData,
-            object2.geometry() as *const GeometryData
+            object1.geometry().unwrap() as *const GeometryData,
+            object2.geometry().unwrap() as *const GeometryData
         );
     }
+
+    #[test]
+    fn metadata() {
+        let mut metadata = Metadata::new();
+
+        metadata.insert("source", MetadataValue::Path(PathBuf::from("scenes/level.blend")));
+        metadata.insert("tags", MetadataValue::Tags(vec!["level".to_owned()]));
+
+        let mut yarn = Yarn::new();
+
+        GeometryData::Geometry(
+            Geometry::new(
+                vec![(1.0, 2.0, 3.0); 10],
+                vec![(0.0, 1.0); 6],
+                vec![(1, 2); 18]
+            ).unwrap()
+        ).tie(&mut yarn);
+        yarn.set_metadata(metadata.clone());
+
+        let bytes = yarn.into_bytes().unwrap();
+
+        assert_eq!(Yarn::metadata_from_bytes(&bytes), Some(metadata.clone()));
+        assert_eq!(Yarn::from_bytes(&bytes).unwrap().metadata(), Some(&metadata));
+
+        let bytes = Yarn::new().into_bytes().unwrap();
+
+        assert_eq!(Yarn::metadata_from_bytes(&bytes), None);
+        assert!(Yarn::from_bytes(&bytes).unwrap().metadata().is_none());
+    }
+
+    #[test]
+    fn version() {
+        let bytes = Yarn::new().into_bytes().unwrap();
+
+        assert!(Yarn::from_bytes(&bytes).is_some());
+
+        // A newer version than this one knows is refused rather than misread.
+        let mut newer = bytes.clone();
+        newer[12] += 1;
+
+        assert!(Yarn::from_bytes(&newer).is_none());
+        assert!(Yarn::metadata_from_bytes(&newer).is_none());
+    }
+
+    fn geometry(x: f32) -> Rc<GeometryData> {
+        Rc::new(
+            GeometryData::Geometry(
+                Geometry::new(
+                    vec![(x, 2.0, 3.0); 10],
+                    vec![(0.0, 1.0); 6],
+                    vec![(1, 2); 18]
+                ).unwrap()
+            )
+        )
+    }
+
+    fn x(object: &Object) -> f32 {
+        match *object.geometry().unwrap() {
+            GeometryData::Geometry(ref geometry) => geometry.vertices()[0].0,
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn tie_drop_reuse() {
+        let shared = geometry(1.0);
+        let object1 = Object::new(shared.clone());
+        let object2 = Object::new(shared.clone());
+        let object3 = Object::new(shared);
+
+        let mut yarn = Yarn::new();
+
+        object1.tie(&mut yarn);
+        object2.tie(&mut yarn);
+        drop(object3);
+
+        // Likely to land in the allocation freed by `object3` if the yarn did not hold on to it.
+        let object4 = Object::new(geometry(4.0));
+        object4.tie(&mut yarn);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+
+        let object1 = Object::untie(&mut yarn).unwrap();
+        let object2 = Object::untie(&mut yarn).unwrap();
+        let object4 = Object::untie(&mut yarn).unwrap();
+
+        assert_eq!(x(&object1), 1.0);
+        assert_eq!(x(&object2), 1.0);
+        assert_eq!(x(&object4), 4.0);
+
+        assert!(ptr::eq(object1.geometry().unwrap(), object2.geometry().unwrap()));
+        assert!(!ptr::eq(object1.geometry().unwrap(), object4.geometry().unwrap()));
+    }
+
+    #[test]
+    fn tie_interleaved() {
+        let shared1 = geometry(1.0);
+        let shared2 = geometry(2.0);
+
+        let mut yarn = Yarn::new();
+
+        Object::new(shared1.clone()).tie(&mut yarn);
+        Object::new(shared2.clone()).tie(&mut yarn);
+        Object::new(shared1.clone()).tie(&mut yarn);
+        drop(shared1);
+        Object::new(geometry(3.0)).tie(&mut yarn);
+        Object::new(shared2).tie(&mut yarn);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+
+        let xs: Vec<_> = (0..5).map(|_| x(&Object::untie(&mut yarn).unwrap())).collect();
+
+        assert_eq!(xs, vec![1.0, 2.0, 1.0, 3.0, 2.0]);
+        assert!(Object::untie(&mut yarn).is_none());
+    }
+
+    #[test]
+    fn tie_dropped_owner() {
+        let shared = geometry(1.0);
+        let object = Object::new(shared.clone());
+
+        let mut yarn = Yarn::new();
+
+        object.tie(&mut yarn);
+
+        assert!(yarn.is_entangled());
+
+        drop(shared);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+
+        assert_eq!(x(&Object::untie(&mut yarn).unwrap()), 1.0);
+    }
+
+    #[test]
+    fn roots_in_order() {
+        let mut yarn = Yarn::new();
+
+        Object::new(geometry(1.0)).tie(&mut yarn);
+        GeometryData::Geometry(
+            Geometry::new(
+                vec![(2.0, 2.0, 3.0); 10],
+                vec![(0.0, 1.0); 6],
+                vec![(1, 2); 18]
+            ).unwrap()
+        ).tie(&mut yarn);
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+
+        assert_eq!(yarn.len_blocks(), 3);
+        assert_eq!(yarn.len_roots(), 2);
+        assert!(GeometryData::untie(&mut yarn).is_none());
+        assert_eq!(x(&Object::untie(&mut yarn).unwrap()), 1.0);
+        assert!(Object::untie(&mut yarn).is_none());
+        assert!(GeometryData::untie(&mut yarn).is_some());
+        assert_eq!(yarn.len_roots(), 0);
+    }
+
+    #[test]
+    fn untie_into_bytes() {
+        let mut yarn = Yarn::new();
+
+        GeometryData::Geometry(Geometry::new(vec![(1.0, 2.0, 3.0)], vec![(0.0, 1.0)], vec![(0, 0); 3]).unwrap()).tie(&mut yarn);
+        Object::new(geometry(2.0)).tie(&mut yarn);
+
+        assert!(GeometryData::untie(&mut yarn).is_some());
+
+        let mut yarn = Yarn::from_bytes(&yarn.into_bytes().unwrap()).unwrap();
+
+        assert_eq!(yarn.len_blocks(), 3);
+        assert_eq!(yarn.len_roots(), 1);
+        assert_eq!(x(&Object::untie(&mut yarn).unwrap()), 2.0);
+
+        // Both freed root slots are handed out again after loading.
+        Object::new(geometry(3.0)).tie(&mut yarn);
+
+        assert_eq!(yarn.len_blocks(), 3);
+    }
+
+    #[test]
+    fn reuse_free_blocks() {
+        let mut yarn = Yarn::new();
+
+        for i in 0..10 {
+            Object::new(geometry(i as f32)).tie(&mut yarn);
+
+            assert_eq!(x(&Object::untie(&mut yarn).unwrap()), i as f32);
+        }
+
+        // One geometry block per iteration plus the single recycled root block.
+        assert_eq!(yarn.len_blocks(), 11);
+    }
 }